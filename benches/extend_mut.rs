@@ -0,0 +1,62 @@
+//! Compares `extend_mut` with calling the closure directly, to keep an eye on the cost
+//! of the unwind guard and of the identity check.
+
+use std::hint::black_box;
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use extend_mut::{ExtendMut, extend_mut, extend_mut_unchecked};
+
+#[inline(never)]
+fn bump(counter: &mut u64) -> &mut u64 {
+    *black_box(&mut *counter) += 1;
+    counter
+}
+
+fn single(c: &mut Criterion) {
+    let mut group = c.benchmark_group("single");
+    let mut counter = 0u64;
+
+    group.bench_function("direct", |b| {
+        b.iter(|| {
+            bump(black_box(&mut counter));
+        })
+    });
+    group.bench_function("extend_mut", |b| {
+        b.iter(|| extend_mut(black_box(&mut counter), bump))
+    });
+    group.bench_function("extend_mut_unchecked", |b| {
+        b.iter(|| unsafe { extend_mut_unchecked(black_box(&mut counter), bump) })
+    });
+    group.finish();
+}
+
+fn tuple(c: &mut Criterion) {
+    let mut group = c.benchmark_group("tuple");
+    let mut counters = [0u64; 4];
+
+    group.bench_function("direct", |b| {
+        b.iter(|| {
+            let [a, b, c, d] = black_box(&mut counters);
+            black_box((bump(a), bump(b), bump(c), bump(d)));
+        })
+    });
+    group.bench_function("extend_mut", |b| {
+        b.iter(|| {
+            let [a, b, c, d] = black_box(&mut counters);
+            (a, b, c, d).extend_mut(|(a, b, c, d)| (bump(a), bump(b), bump(c), bump(d)))
+        })
+    });
+    group.bench_function("extend_mut_unchecked", |b| {
+        b.iter(|| {
+            let [a, b, c, d] = black_box(&mut counters);
+            unsafe {
+                (a, b, c, d)
+                    .extend_mut_unchecked(|(a, b, c, d)| (bump(a), bump(b), bump(c), bump(d)))
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, single, tuple);
+criterion_main!(benches);
@@ -1,61 +1,190 @@
+use core::fmt;
+#[cfg(not(feature = "std"))]
+use core::marker::PhantomData;
+#[cfg(feature = "std")]
+use core::sync::atomic::{AtomicPtr, Ordering};
+
+use crate::ExtendMutError;
+
+/// Information about an abort, passed to the abort hook.
+///
+/// See `set_abort_hook` for `std` and `abort_hook!` for the `extern-abort-hook` feature.
+///
+/// It does not carry the payload of a panic that unwound out of the closure. Only the
+/// panic hook reports that, see [`ExtendMutError::Unwound`].
+#[derive(Debug)]
+pub struct AbortInfo<'a> {
+    error: ExtendMutError,
+    #[cfg(feature = "std")]
+    backtrace: &'a std::backtrace::Backtrace,
+    #[cfg(not(feature = "std"))]
+    _lifetime: PhantomData<&'a ()>,
+}
+
+impl<'a> AbortInfo<'a> {
+    /// What went wrong, and where.
+    pub fn error(&self) -> &ExtendMutError {
+        &self.error
+    }
+
+    /// Backtrace captured right before aborting.
+    #[cfg(feature = "std")]
+    pub fn backtrace(&self) -> &'a std::backtrace::Backtrace {
+        self.backtrace
+    }
+}
+
+impl fmt::Display for AbortInfo<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.error)
+    }
+}
+
+/// Function called right before the process is aborted.
+///
+/// The hook cannot prevent the abort. If it returns or unwinds, the process is aborted anyway.
+#[cfg(feature = "std")]
+pub type AbortHook = fn(&AbortInfo<'_>);
+
+#[cfg(feature = "std")]
+static ABORT_HOOK: AtomicPtr<()> = AtomicPtr::new(core::ptr::null_mut());
+
+/// Registers a hook to be called before `extend_mut` aborts the process, replacing the
+/// previous one. The default behaviour is to print the message to stderr.
+///
+/// This is called when a closure returns a different reference, when it unwinds, and
+/// when an `ExtendMutFuture` is dropped before it is ready.
+///
+/// ```
+/// # #[cfg(feature = "extern-abort-hook")] extend_mut::abort_hook!(|_| {});
+/// extend_mut::set_abort_hook(|info| {
+///     eprintln!("flushing telemetry before abort: {info}");
+/// });
+/// # extend_mut::take_abort_hook();
+/// ```
+#[cfg(feature = "std")]
+pub fn set_abort_hook(hook: AbortHook) {
+    ABORT_HOOK.store(hook as *mut (), Ordering::Release);
+}
+
+/// Unregisters the current abort hook and returns it, restoring the default behaviour.
+#[cfg(feature = "std")]
+pub fn take_abort_hook() -> Option<AbortHook> {
+    hook_from_ptr(ABORT_HOOK.swap(core::ptr::null_mut(), Ordering::AcqRel))
+}
+
 #[cfg(feature = "std")]
-#[cfg(not(test))]
-use core::panic::AssertUnwindSafe;
+fn hook_from_ptr(hook: *mut ()) -> Option<AbortHook> {
+    // SAFETY: only `set_abort_hook` stores non-null pointers, and those are `AbortHook`s.
+    (!hook.is_null()).then(|| unsafe { core::mem::transmute::<*mut (), AbortHook>(hook) })
+}
+
+#[cfg(feature = "extern-abort-hook")]
+unsafe extern "Rust" {
+    safe fn __extend_mut_abort_hook(info: &AbortInfo<'_>);
+}
+
+/// Defines the abort hook for builds with the `extern-abort-hook` feature.
+///
+/// The hook is resolved at link time, so exactly one crate in every binary must invoke
+/// this macro, test binaries and doctests included. If the hook returns or unwinds, a
+/// double panic aborts the process. Under `std` the hook is called first, then the one
+/// registered with `set_abort_hook` or the default message, and the process is aborted
+/// even if it unwinds.
+///
+/// The hook is a function or a closure that captures nothing.
+///
+/// ```ignore
+/// fn on_abort(info: &extend_mut::AbortInfo<'_>) {
+///     log_to_ring_buffer(info.error());
+///     reset_mcu();
+/// }
+///
+/// extend_mut::abort_hook!(on_abort);
+/// ```
+#[cfg(feature = "extern-abort-hook")]
+#[macro_export]
+macro_rules! abort_hook {
+    ($hook:expr) => {
+        #[unsafe(no_mangle)]
+        fn __extend_mut_abort_hook(info: &$crate::AbortInfo<'_>) {
+            let hook: fn(&$crate::AbortInfo<'_>) = $hook;
+            hook(info)
+        }
+    };
+}
 
 #[cfg(not(feature = "std"))]
-#[inline(always)]
-pub fn abort_no_unwind(msg: &'static str) -> ! {
-    struct DoublePanic(&'static str);
-    impl Drop for DoublePanic {
+#[cold]
+pub fn abort_no_unwind(error: ExtendMutError) -> ! {
+    let info = AbortInfo {
+        error,
+        _lifetime: PhantomData,
+    };
+
+    struct DoublePanic<'a>(&'a AbortInfo<'a>);
+    impl Drop for DoublePanic<'_> {
         fn drop(&mut self) {
             panic!("{}", self.0);
         }
     }
 
-    let _double_panic = DoublePanic(msg);
-    // If panic=abort, `msg` will be directly delivered to the panic handler, no double panic.
+    let _double_panic = DoublePanic(&info);
+    #[cfg(feature = "extern-abort-hook")]
+    __extend_mut_abort_hook(&info);
+    // If panic=abort, `info` will be directly delivered to the panic handler, no double panic.
     // If panic=unwind, we will force double panic. This is mostly not needed for no_std.
-    panic!("{msg}");
+    panic!("{info}");
 }
 
 #[cfg(feature = "std")]
-#[inline(always)]
-pub fn abort_no_unwind(msg: &'static str) -> ! {
-    eprintln!("{}", msg);
+#[cold]
+pub fn abort_no_unwind(error: ExtendMutError) -> ! {
+    // Aborts even if the hook unwinds.
+    struct Abort;
+    impl Drop for Abort {
+        fn drop(&mut self) {
+            std::process::abort();
+        }
+    }
+
+    let _abort = Abort;
+    let backtrace = std::backtrace::Backtrace::force_capture();
+    let info = AbortInfo {
+        error,
+        backtrace: &backtrace,
+    };
+    #[cfg(feature = "extern-abort-hook")]
+    __extend_mut_abort_hook(&info);
+    match hook_from_ptr(ABORT_HOOK.load(Ordering::Acquire)) {
+        Some(hook) => hook(&info),
+        None => eprintln!("{info}\nstack backtrace:\n{backtrace}"),
+    }
     std::process::abort();
 }
 
-#[cfg(not(feature = "std"))]
+#[cfg(not(any(test, panic = "abort")))]
 #[inline(always)]
-pub fn abort_on_unwind<T>(f: impl FnOnce() -> T) -> T {
-    // If panic=abort, after panic `f` will go directly to the panic handler.
-    // If panic=unwind, we will force double panic. This is mostly not needed for no_std.
+pub fn abort_on_unwind<T>(f: impl FnOnce() -> T, error: ExtendMutError) -> T {
+    // Only runs if `f` unwinds. Under `std` this calls the hook and aborts, under
+    // `no_std` it panics while unwinding, which aborts too.
 
-    struct DoublePanic;
-    impl Drop for DoublePanic {
+    struct AbortOnDrop(ExtendMutError);
+    impl Drop for AbortOnDrop {
         fn drop(&mut self) {
-            panic!("ExtendMut: Function cannot unwind");
+            abort_no_unwind(self.0);
         }
     }
 
-    let double_panic = DoublePanic;
+    let abort_on_drop = AbortOnDrop(error);
     let ret = f();
-    core::mem::forget(double_panic);
+    core::mem::forget(abort_on_drop);
     ret
 }
 
-#[cfg(test)]
-pub fn abort_on_unwind<T>(f: impl FnOnce() -> T) -> T {
-    f()
-}
-
-#[cfg(feature = "std")]
-#[cfg(not(test))]
+// With `panic = "abort"` nothing can unwind out of `f`, so there is nothing to guard.
+#[cfg(any(test, panic = "abort"))]
 #[inline(always)]
-pub fn abort_on_unwind<T>(f: impl FnOnce() -> T) -> T {
-    match std::panic::catch_unwind(AssertUnwindSafe(f)) {
-        Ok(ret) => ret,
-        // fixme: how can we print error? It is just `Box<dyn Any + Send>`.
-        Err(_err) => abort_no_unwind("ExtendMut: Function cannot unwind"),
-    }
+pub fn abort_on_unwind<T>(f: impl FnOnce() -> T, _error: ExtendMutError) -> T {
+    f()
 }
@@ -0,0 +1,115 @@
+use core::{any::type_name, fmt, panic::Location};
+
+/// Ways in which extending a reference can fail.
+///
+/// All of them abort the process, except [`ExtendMutError::ZeroSized`], which is raised
+/// as a panic before the closure gets the reference. Abort hooks receive it through
+/// [`AbortInfo::error`](crate::AbortInfo::error).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ExtendMutError {
+    /// The closure returned a different reference than the one it was given.
+    PointerChanged {
+        location: &'static Location<'static>,
+        type_name: &'static str,
+    },
+    /// The closure unwound while holding the extended reference.
+    ///
+    /// The panic is not caught, so its payload is not part of the error. Only the panic
+    /// hook (or the panic handler under `no_std`) sees it, and it runs before the abort
+    /// hook. To keep the message for telemetry, record it from a panic hook.
+    Unwound {
+        location: &'static Location<'static>,
+        type_name: &'static str,
+    },
+    /// An `ExtendMutFuture` was dropped before it yielded `Poll::Ready`.
+    DroppedBeforeReady {
+        location: &'static Location<'static>,
+        type_name: &'static str,
+    },
+    /// The value is zero-sized and cannot be lent with a unique address: it is aligned
+    /// to more than 16 bytes, or it is pinned.
+    ZeroSized {
+        location: &'static Location<'static>,
+        type_name: &'static str,
+    },
+}
+
+impl ExtendMutError {
+    #[cfg(any(test, feature = "stable-async"))]
+    #[inline(always)]
+    pub(crate) fn pointer_changed<T: ?Sized>(location: &'static Location<'static>) -> Self {
+        Self::PointerChanged {
+            location,
+            type_name: type_name::<T>(),
+        }
+    }
+
+    #[cfg(feature = "stable-async")]
+    #[inline(always)]
+    pub(crate) fn unwound<T: ?Sized>(location: &'static Location<'static>) -> Self {
+        Self::Unwound {
+            location,
+            type_name: type_name::<T>(),
+        }
+    }
+
+    #[cfg(feature = "stable-async")]
+    #[inline(always)]
+    pub(crate) fn dropped_before_ready<T: ?Sized>(location: &'static Location<'static>) -> Self {
+        Self::DroppedBeforeReady {
+            location,
+            type_name: type_name::<T>(),
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn zero_sized<T: ?Sized>(location: &'static Location<'static>) -> Self {
+        Self::ZeroSized {
+            location,
+            type_name: type_name::<T>(),
+        }
+    }
+
+    /// Where `extend_mut`, `extend_mut_async` or the [`ExtendMut`](crate::ExtendMut)
+    /// method was called.
+    pub fn location(&self) -> &'static Location<'static> {
+        match *self {
+            Self::PointerChanged { location, .. }
+            | Self::Unwound { location, .. }
+            | Self::DroppedBeforeReady { location, .. }
+            | Self::ZeroSized { location, .. } => location,
+        }
+    }
+
+    /// Name of the type whose reference was being extended.
+    pub fn type_name(&self) -> &'static str {
+        match *self {
+            Self::PointerChanged { type_name, .. }
+            | Self::Unwound { type_name, .. }
+            | Self::DroppedBeforeReady { type_name, .. }
+            | Self::ZeroSized { type_name, .. } => type_name,
+        }
+    }
+}
+
+impl fmt::Display for ExtendMutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            Self::PointerChanged { .. } => "ExtendMut: Pointer changed",
+            Self::Unwound { .. } => "ExtendMut: Function cannot unwind",
+            Self::DroppedBeforeReady { .. } => {
+                "Cannot drop ExtendMutFuture before it yields Poll::Ready"
+            }
+            Self::ZeroSized { .. } => "ExtendMut: Zero-sized value cannot be lent here",
+        };
+        write!(
+            f,
+            "{message} (`{}` at {})",
+            self.type_name(),
+            self.location()
+        )
+    }
+}
+
+impl core::error::Error for ExtendMutError {}
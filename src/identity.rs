@@ -0,0 +1,95 @@
+/*!
+
+Identity of lent references.
+
+`extend_mut` proves that the closure did not keep the reference by comparing
+the pointer it gets back with the one it lent out. That only works if no other
+reference can have the same address, which is not true for zero-sized values:
+any aligned address is valid for them, so a closure could return a fresh
+`&'static mut ()` instead of the one it received.
+
+For zero-sized values we lend a pointer into a [`ZstToken`] instead. The token
+is a live local owned by the current call, so the only way to obtain a
+reference to its interior is through the pointer we lent.
+
+*/
+
+use core::{mem::MaybeUninit, panic::Location};
+
+use crate::ExtendMutError;
+
+/// Largest alignment of a zero-sized value that can be lent through a [`ZstToken`].
+pub(crate) const ZST_TOKEN_ALIGN: usize = 16;
+
+/// Storage whose interior address identifies a lent zero-sized reference.
+#[repr(C, align(16))]
+pub(crate) struct ZstToken([MaybeUninit<u8>; ZST_TOKEN_ALIGN + 1]);
+
+impl ZstToken {
+    #[inline(always)]
+    pub(crate) const fn new() -> Self {
+        Self([MaybeUninit::uninit(); ZST_TOKEN_ALIGN + 1])
+    }
+
+    /// Returns the pointer that should be lent out for `ptr`.
+    ///
+    /// For values that are not zero-sized this is `ptr` itself. Zero-sized values are
+    /// re-addressed to the interior of the token, keeping the metadata of `ptr`.
+    ///
+    /// # Safety
+    /// `ptr` must be valid for reads of its metadata, i.e. come from a live reference.
+    #[inline(always)]
+    pub(crate) unsafe fn lend<T: ?Sized>(
+        &mut self,
+        ptr: *mut T,
+        location: &'static Location<'static>,
+    ) -> *mut T {
+        let (size, align) = unsafe { (size_of_val(&*ptr), align_of_val(&*ptr)) };
+        if size != 0 {
+            return ptr;
+        }
+        if align > ZST_TOKEN_ALIGN {
+            panic!("{}", ExtendMutError::zero_sized::<T>(location));
+        }
+        // `+ ZST_TOKEN_ALIGN` is strictly inside the token, so it cannot be the
+        // one-past-the-end address of some other allocation either.
+        ptr.with_addr(self.0.as_mut_ptr().addr() + ZST_TOKEN_ALIGN)
+    }
+}
+
+/// Address and metadata of a lent reference, used to check that the same reference
+/// came back.
+///
+/// The metadata is the length of a slice or `str`, and the vtable of a trait object.
+/// Address and size alone are not enough for trait objects: a closure that can
+/// downcast the value could give back its first field, of the same size if the other
+/// fields are zero-sized, as another trait object and keep the rest. A different vtable
+/// may belong to the same type, but that cannot be told apart, so trait objects must
+/// come back with the vtable they were lent with.
+pub(crate) struct Identity<T: ?Sized> {
+    ptr: *mut T,
+}
+
+// `ptr` stands for the `&mut T` given up by the caller, so this follows `&mut T`. It is
+// only dereferenced to lend it to the closure and compared with what comes back, and
+// nothing else can reach the value until then.
+unsafe impl<T: ?Sized + Send> Send for Identity<T> {}
+unsafe impl<T: ?Sized + Sync> Sync for Identity<T> {}
+
+impl<T: ?Sized> Identity<T> {
+    #[inline(always)]
+    pub(crate) fn new(ptr: *mut T) -> Self {
+        Self { ptr }
+    }
+
+    #[inline(always)]
+    pub(crate) fn ptr(&self) -> *mut T {
+        self.ptr
+    }
+
+    /// Whether `other` refers to the same value that was lent out.
+    #[inline(always)]
+    pub(crate) fn matches(&self, other: &T) -> bool {
+        core::ptr::eq(self.ptr, other)
+    }
+}
@@ -3,162 +3,618 @@
 This module contains implementations for helper traits
 
 IntoExtendMutReturn:
-No impl for IntoExtendMutReturn<(&mut T, &mut T), ()>
+No impl for IntoExtendMutReturn<(&mut T, &mut T), ()>, use `Back` and `With`
+when a tuple is ambiguous.
+
+ExtendMut:
+Only implemented through `Reclaim`. Tuples, arrays, `Option`s and so on are
+handles themselves, so the whole value is lent under one unwind guard and
+checked at once, instead of nesting a closure per element.
 */
 
-use crate::{extend_mut, ExtendMut, IntoExtendMutReturn};
+use core::{any::type_name, cell::RefMut, panic::Location, pin::Pin};
+
+use crate::{
+    Back, ExtendMut, ExtendMutError, IntoExtendMutReturn, RawMut, Reclaim, With,
+    aborts::{abort_no_unwind, abort_on_unwind},
+    reclaim::Internal,
+};
+
+#[cfg(feature = "alloc")]
+use alloc::{boxed::Box, vec::Vec};
+#[cfg(feature = "std")]
+use std::sync::{MutexGuard, RwLockWriteGuard};
 
-#[cfg(feature = "assume-non-forget")]
-use crate::extend_mut_async;
+#[cfg(feature = "async")]
+use core::{
+    future::Future,
+    marker::{PhantomData, PhantomPinned},
+    task::{Context, Poll},
+};
 
 // #![feature(generic_const_exprs)]
 // trait NotZst: Sized {}
 // impl<T> NotZst for T where [(); size_of::<T>() - 1]: Sized {}
 
-macro_rules! impl_into_extend_mut {
-    (unit: $head:ident,) => {
-        unsafe impl<'a, $head: ?Sized> IntoExtendMutReturn<(&'a mut $head,), ()> for (&'a mut $head,) {
+macro_rules! impl_reclaim_tuple {
+    ($($param:ident $idx:tt,)+) => {
+        unsafe impl<'b, $($param: Reclaim<'b>,)+> Reclaim<'b> for ($($param,)+) {
+            type Extended = ($($param::Extended,)+);
+            type Raw = ($($param::Raw,)+);
             #[inline(always)]
-            fn into_extend_mut_return(self) -> ((&'a mut $head,), ()) { (self, ()) }
-        }
-    };
-    (unit: $head:ident, $($param:ident,)*) => {
-        unsafe impl<'a, $head: ?Sized, $($param,)*> IntoExtendMutReturn<(&'a mut $head, $(&'a mut $param,)*), ()>
-            for (&'a mut $head, $(&'a mut $param,)*)
-        {
+            #[track_caller]
+            fn into_raw(self) -> Self::Raw {
+                self.into_raw_at(Location::caller(), Internal)
+            }
             #[inline(always)]
-            fn into_extend_mut_return(self) -> ((&'a mut $head, $(&'a mut $param,)*), ()) { (self, ()) }
-        }
-        impl_into_extend_mut!(unit: $($param,)*);
-    };
-    (any: $head:ident,) => {
-        unsafe impl<'a, $head: ?Sized, R> IntoExtendMutReturn<(&'a mut $head,), R> for ((&'a mut $head,), R) {
+            fn into_raw_at(self, location: &'static Location<'static>, _: Internal) -> Self::Raw {
+                ($(self.$idx.into_raw_at(location, Internal),)+)
+            }
             #[inline(always)]
-            fn into_extend_mut_return(self) -> ((&'a mut $head,), R) { self }
-        }
-    };
-    (any: $head:ident, $($param:ident,)*) => {
-        unsafe impl<'a, $head: ?Sized, $($param: ?Sized,)* R> IntoExtendMutReturn<(&'a mut $head, $(&'a mut $param,)*), R>
-            for ((&'a mut $head, $(&'a mut $param,)*), R)
-        {
+            unsafe fn extend(raw: &mut Self::Raw) -> Self::Extended {
+                unsafe { ($($param::extend(&mut raw.$idx),)+) }
+            }
             #[inline(always)]
-            fn into_extend_mut_return(self) -> ((&'a mut $head, $(&'a mut $param,)*), R) { self }
+            fn is_reclaimed(raw: &Self::Raw, extended: Self::Extended) -> bool {
+                Self::check_reclaimed(raw, extended, Internal).is_ok()
+            }
+            #[inline(always)]
+            fn check_reclaimed(raw: &Self::Raw, extended: Self::Extended, _: Internal) -> Result<(), &'static str> {
+                $($param::check_reclaimed(&raw.$idx, extended.$idx, Internal)?;)+
+                Ok(())
+            }
         }
-        impl_into_extend_mut!(any: $($param,)*);
     };
 }
 
-macro_rules! impl_extend_mut_many {
-    ($head:ident,) => {
-        #[allow(non_snake_case)]
-        impl<'a, 'b, $head: ?Sized + 'b> ExtendMut<'b> for (&'a mut $head,) {
-            type Extended = (&'b mut $head,);
+// Guards are taken by value and dropped once the reference is back.
+macro_rules! impl_reclaim_guard {
+    ($($(#[$meta:meta])* $guard:ident,)*) => {$(
+        $(#[$meta])*
+        unsafe impl<'a, 'b, T: ?Sized + 'b> Reclaim<'b> for $guard<'a, T> {
+            type Extended = &'b mut T;
+            // The target lives behind the lock, so it stays put when the guard moves.
+            type Raw = (RawMut<T>, $guard<'a, T>);
             #[inline(always)]
-            fn extend_mut<R, ER: IntoExtendMutReturn<Self::Extended, R>>(
-                self,
-                f: impl FnOnce(Self::Extended) -> ER,
-            ) -> R {
-                extend_mut(self.0, #[inline(always)] |x| {
-                    let ((x,), r) = f((x,)).into_extend_mut_return();
-                    (x, r)
-                })
+            #[track_caller]
+            fn into_raw(self) -> Self::Raw {
+                self.into_raw_at(Location::caller(), Internal)
             }
-            #[cfg(feature = "assume-non-forget")]
             #[inline(always)]
-            async fn extend_mut_async<R, ER: IntoExtendMutReturn<Self::Extended, R>>(
-                self,
-                f: impl AsyncFnOnce(Self::Extended) -> ER,
-            ) -> R {
-                extend_mut_async(self.0, #[inline(always)] async |x| {
-                    let ((x,), r) = f((x,)).await.into_extend_mut_return();
-                    (x, r)
-                }).await
+            fn into_raw_at(mut self, location: &'static Location<'static>, _: Internal) -> Self::Raw {
+                (RawMut::new(&mut *self, location), self)
             }
-        }
-    };
-    ($head:ident, $($param:ident,)*) => {
-        #[allow(non_snake_case)]
-        impl <'a, 'b, $head: ?Sized + 'b, $($param: ?Sized + 'b,)*> ExtendMut<'b> for (&'a mut $head, $(&'a mut $param,)*) {
-            type Extended = (&'b mut $head, $(&'b mut $param,)*);
             #[inline(always)]
-            fn extend_mut<R, ER: IntoExtendMutReturn<Self::Extended, R>>( self, f: impl FnOnce(Self::Extended) -> ER,) -> R {
-                let (x, $($param,)*) = self;
-                extend_mut(x, #[inline(always)] |x| {
-                    ($($param,)*).extend_mut(#[inline(always)] |($($param,)*)| {
-                        let ((x, $($param,)*), r) = f((x, $($param,)*)).into_extend_mut_return();
-                        (($($param,)*), (x, r))
-                    })
-                })
+            unsafe fn extend(raw: &mut Self::Raw) -> Self::Extended {
+                unsafe { &mut *raw.0.lend() }
             }
-            #[cfg(feature = "assume-non-forget")]
             #[inline(always)]
-            async fn extend_mut_async<R, ER: IntoExtendMutReturn<Self::Extended, R>>( self, f: impl AsyncFnOnce(Self::Extended) -> ER,) -> R {
-                let (x, $($param,)*) = self;
-                extend_mut_async(x, #[inline(always)] async |x| {
-                    ($($param,)*).extend_mut_async(#[inline(always)] async |($($param,)*)| {
-                        let ((x, $($param,)*), r) = f((x, $($param,)*)).await.into_extend_mut_return();
-                        (($($param,)*), (x, r))
-                    }).await
-                }).await
+            fn is_reclaimed(raw: &Self::Raw, extended: Self::Extended) -> bool {
+                raw.0.matches(extended)
+            }
+            fn type_name(_: Internal) -> &'static str {
+                type_name::<T>()
             }
         }
-        impl_extend_mut_many!($($param,)*);
-    };
+    )*};
+}
+
+unsafe impl<T> IntoExtendMutReturn<T, ()> for Back<T> {
+    #[inline(always)]
+    fn into_extend_mut_return(self) -> (T, ()) {
+        (self.0, ())
+    }
 }
 
-unsafe impl<'a, T: ?Sized, R> IntoExtendMutReturn<&'a mut T, R> for (&'a mut T, R) {
+unsafe impl<T, R> IntoExtendMutReturn<T, R> for With<T, R> {
     #[inline(always)]
-    fn into_extend_mut_return(self) -> (&'a mut T, R) {
+    fn into_extend_mut_return(self) -> (T, R) {
+        (self.0, self.1)
+    }
+}
+
+// Covers `(&mut T, R)`, `(Pin<&mut T>, R)` and tuples of them with `R`.
+unsafe impl<E, R> IntoExtendMutReturn<E, R> for (E, R) {
+    #[inline(always)]
+    fn into_extend_mut_return(self) -> (E, R) {
         self
     }
 }
 
-unsafe impl<'a, T: ?Sized> IntoExtendMutReturn<&'a mut T, ()> for &'a mut T {
+// Covers every handle given back alone: references, tuples, arrays, `Option`s and so on.
+unsafe impl<'b, H: Reclaim<'b>> IntoExtendMutReturn<H, ()> for H {
     #[inline(always)]
-    fn into_extend_mut_return(self) -> (&'a mut T, ()) {
+    fn into_extend_mut_return(self) -> (H, ()) {
         (self, ())
     }
 }
 
-impl_into_extend_mut!(any: T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12, T13,);
-impl_into_extend_mut!(unit: T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12, T13,);
-
-impl<'a, 'b, T: ?Sized + 'b> ExtendMut<'b> for &'a mut T {
+unsafe impl<'b, T: ?Sized + 'b> Reclaim<'b> for &mut T {
     type Extended = &'b mut T;
+    type Raw = RawMut<T>;
+    #[inline(always)]
+    #[track_caller]
+    fn into_raw(self) -> Self::Raw {
+        self.into_raw_at(Location::caller(), Internal)
+    }
+    #[inline(always)]
+    fn into_raw_at(self, location: &'static Location<'static>, _: Internal) -> Self::Raw {
+        RawMut::new(self, location)
+    }
+    #[inline(always)]
+    unsafe fn extend(raw: &mut Self::Raw) -> Self::Extended {
+        unsafe { &mut *raw.lend() }
+    }
+    #[inline(always)]
+    fn is_reclaimed(raw: &Self::Raw, extended: Self::Extended) -> bool {
+        // We are checking both address and size, because for slices one might make a split.
+        raw.matches(extended)
+    }
+    fn type_name(_: Internal) -> &'static str {
+        type_name::<T>()
+    }
+}
+
+unsafe impl<'b, T: ?Sized + 'b> Reclaim<'b> for Pin<&mut T> {
+    type Extended = Pin<&'b mut T>;
+    type Raw = RawMut<T>;
+    #[inline(always)]
+    #[track_caller]
+    fn into_raw(self) -> Self::Raw {
+        self.into_raw_at(Location::caller(), Internal)
+    }
+    #[inline(always)]
+    fn into_raw_at(self, location: &'static Location<'static>, _: Internal) -> Self::Raw {
+        // SAFETY: the value is never moved, it is only ever lent out pinned.
+        let mut_ref = unsafe { Pin::get_unchecked_mut(self) };
+        // Zero-sized values are lent at another address, which would break pinning.
+        if size_of_val::<T>(mut_ref) == 0 {
+            panic!("{}", ExtendMutError::zero_sized::<T>(location));
+        }
+        RawMut::new(mut_ref, location)
+    }
+    #[inline(always)]
+    unsafe fn extend(raw: &mut Self::Raw) -> Self::Extended {
+        unsafe { Pin::new_unchecked(&mut *raw.lend()) }
+    }
+    #[inline(always)]
+    fn is_reclaimed(raw: &Self::Raw, extended: Self::Extended) -> bool {
+        let extended: &mut T = unsafe { Pin::into_inner_unchecked(extended) };
+        raw.matches(extended)
+    }
+    fn type_name(_: Internal) -> &'static str {
+        type_name::<T>()
+    }
+}
+
+impl_reclaim_tuple!(T1 0,);
+impl_reclaim_tuple!(T1 0, T2 1,);
+impl_reclaim_tuple!(T1 0, T2 1, T3 2,);
+impl_reclaim_tuple!(T1 0, T2 1, T3 2, T4 3,);
+impl_reclaim_tuple!(T1 0, T2 1, T3 2, T4 3, T5 4,);
+impl_reclaim_tuple!(T1 0, T2 1, T3 2, T4 3, T5 4, T6 5,);
+impl_reclaim_tuple!(T1 0, T2 1, T3 2, T4 3, T5 4, T6 5, T7 6,);
+impl_reclaim_tuple!(T1 0, T2 1, T3 2, T4 3, T5 4, T6 5, T7 6, T8 7,);
+impl_reclaim_tuple!(T1 0, T2 1, T3 2, T4 3, T5 4, T6 5, T7 6, T8 7, T9 8,);
+impl_reclaim_tuple!(T1 0, T2 1, T3 2, T4 3, T5 4, T6 5, T7 6, T8 7, T9 8, T10 9,);
+impl_reclaim_tuple!(T1 0, T2 1, T3 2, T4 3, T5 4, T6 5, T7 6, T8 7, T9 8, T10 9, T11 10,);
+impl_reclaim_tuple!(T1 0, T2 1, T3 2, T4 3, T5 4, T6 5, T7 6, T8 7, T9 8, T10 9, T11 10, T12 11,);
+impl_reclaim_tuple!(T1 0, T2 1, T3 2, T4 3, T5 4, T6 5, T7 6, T8 7, T9 8, T10 9, T11 10, T12 11, T13 12,);
+
+unsafe impl<'b> Reclaim<'b> for () {
+    type Extended = ();
+    type Raw = ();
+    #[inline(always)]
+    fn into_raw(self) {}
+    #[inline(always)]
+    unsafe fn extend(_: &mut ()) {}
+    #[inline(always)]
+    fn is_reclaimed(_: &(), _: ()) -> bool {
+        true
+    }
+}
+
+unsafe impl<'b, H: Reclaim<'b>, const N: usize> Reclaim<'b> for [H; N] {
+    type Extended = [H::Extended; N];
+    type Raw = [H::Raw; N];
+    #[inline(always)]
+    #[track_caller]
+    fn into_raw(self) -> Self::Raw {
+        self.into_raw_at(Location::caller(), Internal)
+    }
+    #[inline(always)]
+    fn into_raw_at(self, location: &'static Location<'static>, _: Internal) -> Self::Raw {
+        self.map(|handle| handle.into_raw_at(location, Internal))
+    }
+    #[inline(always)]
+    unsafe fn extend(raw: &mut Self::Raw) -> Self::Extended {
+        raw.each_mut().map(|raw| unsafe { H::extend(raw) })
+    }
+    #[inline(always)]
+    fn is_reclaimed(raw: &Self::Raw, extended: Self::Extended) -> bool {
+        Self::check_reclaimed(raw, extended, Internal).is_ok()
+    }
+    #[inline(always)]
+    fn check_reclaimed(
+        raw: &Self::Raw,
+        extended: Self::Extended,
+        _: Internal,
+    ) -> Result<(), &'static str> {
+        check_all::<H>(raw, extended)
+    }
+    fn type_name(_: Internal) -> &'static str {
+        H::type_name(Internal)
+    }
+}
+
+#[cfg(feature = "alloc")]
+unsafe impl<'b, H: Reclaim<'b>> Reclaim<'b> for Vec<H> {
+    type Extended = Vec<H::Extended>;
+    // Raw handles live on the heap, so they stay put even if the vector moves.
+    type Raw = Vec<H::Raw>;
+    #[inline(always)]
+    #[track_caller]
+    fn into_raw(self) -> Self::Raw {
+        self.into_raw_at(Location::caller(), Internal)
+    }
+    #[inline(always)]
+    fn into_raw_at(self, location: &'static Location<'static>, _: Internal) -> Self::Raw {
+        self.into_iter()
+            .map(|handle| handle.into_raw_at(location, Internal))
+            .collect()
+    }
+    #[inline(always)]
+    unsafe fn extend(raw: &mut Self::Raw) -> Self::Extended {
+        raw.iter_mut()
+            .map(|raw| unsafe { H::extend(raw) })
+            .collect()
+    }
+    #[inline(always)]
+    fn is_reclaimed(raw: &Self::Raw, extended: Self::Extended) -> bool {
+        Self::check_reclaimed(raw, extended, Internal).is_ok()
+    }
+    #[inline(always)]
+    fn check_reclaimed(
+        raw: &Self::Raw,
+        extended: Self::Extended,
+        _: Internal,
+    ) -> Result<(), &'static str> {
+        check_all::<H>(raw, extended)
+    }
+    fn type_name(_: Internal) -> &'static str {
+        H::type_name(Internal)
+    }
+}
+
+#[cfg(feature = "alloc")]
+unsafe impl<'b, H: Reclaim<'b>> Reclaim<'b> for Box<[H]> {
+    type Extended = Box<[H::Extended]>;
+    type Raw = Box<[H::Raw]>;
+    #[inline(always)]
+    #[track_caller]
+    fn into_raw(self) -> Self::Raw {
+        self.into_raw_at(Location::caller(), Internal)
+    }
+    #[inline(always)]
+    fn into_raw_at(self, location: &'static Location<'static>, _: Internal) -> Self::Raw {
+        self.into_vec()
+            .into_iter()
+            .map(|handle| handle.into_raw_at(location, Internal))
+            .collect()
+    }
     #[inline(always)]
+    unsafe fn extend(raw: &mut Self::Raw) -> Self::Extended {
+        raw.iter_mut()
+            .map(|raw| unsafe { H::extend(raw) })
+            .collect()
+    }
+    #[inline(always)]
+    fn is_reclaimed(raw: &Self::Raw, extended: Self::Extended) -> bool {
+        Self::check_reclaimed(raw, extended, Internal).is_ok()
+    }
+    #[inline(always)]
+    fn check_reclaimed(
+        raw: &Self::Raw,
+        extended: Self::Extended,
+        _: Internal,
+    ) -> Result<(), &'static str> {
+        check_all::<H>(raw, extended.into_vec())
+    }
+    fn type_name(_: Internal) -> &'static str {
+        H::type_name(Internal)
+    }
+}
+
+/// Checks that `extended` yields exactly the handles lent out, in the same order.
+#[inline(always)]
+fn check_all<'b, H: Reclaim<'b>>(
+    raw: &[H::Raw],
+    extended: impl IntoIterator<Item = H::Extended>,
+) -> Result<(), &'static str> {
+    let mut extended = extended.into_iter();
+    for raw in raw {
+        match extended.next() {
+            Some(extended) => H::check_reclaimed(raw, extended, Internal)?,
+            None => return Err(H::type_name(Internal)),
+        }
+    }
+    match extended.next() {
+        Some(_) => Err(H::type_name(Internal)),
+        None => Ok(()),
+    }
+}
+
+unsafe impl<'b, H: Reclaim<'b>> Reclaim<'b> for Option<H> {
+    type Extended = Option<H::Extended>;
+    type Raw = Option<H::Raw>;
+    #[inline(always)]
+    #[track_caller]
+    fn into_raw(self) -> Self::Raw {
+        self.into_raw_at(Location::caller(), Internal)
+    }
+    #[inline(always)]
+    fn into_raw_at(self, location: &'static Location<'static>, _: Internal) -> Self::Raw {
+        self.map(|handle| handle.into_raw_at(location, Internal))
+    }
+    #[inline(always)]
+    unsafe fn extend(raw: &mut Self::Raw) -> Self::Extended {
+        raw.as_mut().map(|raw| unsafe { H::extend(raw) })
+    }
+    #[inline(always)]
+    fn is_reclaimed(raw: &Self::Raw, extended: Self::Extended) -> bool {
+        Self::check_reclaimed(raw, extended, Internal).is_ok()
+    }
+    #[inline(always)]
+    fn check_reclaimed(
+        raw: &Self::Raw,
+        extended: Self::Extended,
+        _: Internal,
+    ) -> Result<(), &'static str> {
+        match (raw, extended) {
+            (Some(raw), Some(extended)) => H::check_reclaimed(raw, extended, Internal),
+            (None, None) => Ok(()),
+            _ => Err(type_name::<Self>()),
+        }
+    }
+    fn type_name(_: Internal) -> &'static str {
+        H::type_name(Internal)
+    }
+}
+
+unsafe impl<'b, H: Reclaim<'b>, Err> Reclaim<'b> for Result<H, Err> {
+    type Extended = Result<H::Extended, Err>;
+    // The error is moved out by `extend`, which is called at most once.
+    type Raw = Result<H::Raw, Option<Err>>;
+    #[inline(always)]
+    #[track_caller]
+    fn into_raw(self) -> Self::Raw {
+        self.into_raw_at(Location::caller(), Internal)
+    }
+    #[inline(always)]
+    fn into_raw_at(self, location: &'static Location<'static>, _: Internal) -> Self::Raw {
+        self.map(|handle| handle.into_raw_at(location, Internal))
+            .map_err(Some)
+    }
+    #[inline(always)]
+    unsafe fn extend(raw: &mut Self::Raw) -> Self::Extended {
+        match raw {
+            Ok(raw) => Ok(unsafe { H::extend(raw) }),
+            Err(error) => Err(unsafe { error.take().unwrap_unchecked() }),
+        }
+    }
+    #[inline(always)]
+    fn is_reclaimed(raw: &Self::Raw, extended: Self::Extended) -> bool {
+        Self::check_reclaimed(raw, extended, Internal).is_ok()
+    }
+    #[inline(always)]
+    fn check_reclaimed(
+        raw: &Self::Raw,
+        extended: Self::Extended,
+        _: Internal,
+    ) -> Result<(), &'static str> {
+        match (raw, extended) {
+            (Ok(raw), Ok(extended)) => H::check_reclaimed(raw, extended, Internal),
+            // The error is not lent out, so it may come back changed.
+            (Err(_), Err(_)) => Ok(()),
+            _ => Err(type_name::<Self>()),
+        }
+    }
+    fn type_name(_: Internal) -> &'static str {
+        H::type_name(Internal)
+    }
+}
+
+impl_reclaim_guard!(
+    RefMut,
+    #[cfg(feature = "std")]
+    MutexGuard,
+    #[cfg(feature = "std")]
+    RwLockWriteGuard,
+);
+
+impl<'b, H: Reclaim<'b>> ExtendMut<'b> for H {
+    type Extended = H::Extended;
+    #[inline(always)]
+    #[track_caller]
     fn extend_mut<R, ER: IntoExtendMutReturn<Self::Extended, R>>(
         self,
         f: impl FnOnce(Self::Extended) -> ER,
     ) -> R {
-        extend_mut(self, f)
+        self.extend_mut_at(f, Location::caller())
     }
-    #[cfg(feature = "assume-non-forget")]
     #[inline(always)]
-    async fn extend_mut_async<R, ER: IntoExtendMutReturn<Self::Extended, R>>(
+    fn extend_mut_at<R, ER: IntoExtendMutReturn<Self::Extended, R>>(
         self,
-        f: impl AsyncFnOnce(Self::Extended) -> ER,
+        f: impl FnOnce(Self::Extended) -> ER,
+        location: &'static Location<'static>,
     ) -> R {
-        extend_mut_async(self, f).await
-    }
-}
+        let mut raw = self.into_raw_at(location, Internal);
+        let extended = unsafe { H::extend(&mut raw) };
+        let ret = abort_on_unwind(
+            #[inline(always)]
+            move || f(extended),
+            ExtendMutError::Unwound {
+                location,
+                type_name: H::type_name(Internal),
+            },
+        );
+        let (extended, next) = ret.into_extend_mut_return();
 
-impl<'b> ExtendMut<'b> for () {
-    type Extended = ();
+        if let Err(type_name) = H::check_reclaimed(&raw, extended, Internal) {
+            abort_no_unwind(ExtendMutError::PointerChanged {
+                location,
+                type_name,
+            });
+        }
+
+        next
+    }
     #[inline(always)]
-    fn extend_mut<R, ER: IntoExtendMutReturn<Self::Extended, R>>(
+    unsafe fn extend_mut_unchecked_at<R, ER: IntoExtendMutReturn<Self::Extended, R>>(
         self,
         f: impl FnOnce(Self::Extended) -> ER,
+        location: &'static Location<'static>,
     ) -> R {
-        f(()).into_extend_mut_return().1
+        if cfg!(debug_assertions) {
+            return self.extend_mut_at(f, location);
+        }
+        let mut raw = self.into_raw_at(location, Internal);
+        let (_, next) = f(unsafe { H::extend(&mut raw) }).into_extend_mut_return();
+        next
     }
-    #[cfg(feature = "assume-non-forget")]
+    #[cfg(all(feature = "async", feature = "assume-non-forget"))]
     #[inline(always)]
-    async fn extend_mut_async<R, ER: IntoExtendMutReturn<Self::Extended, R>>(
+    #[track_caller]
+    fn extend_mut_async<R, ER: IntoExtendMutReturn<Self::Extended, R>>(
         self,
         f: impl AsyncFnOnce(Self::Extended) -> ER,
-    ) -> R {
-        f(()).await.into_extend_mut_return().1
+    ) -> impl Future<Output = R> {
+        self.extend_mut_async_at(f, Location::caller())
+    }
+    #[cfg(all(feature = "async", feature = "assume-non-forget"))]
+    #[inline(always)]
+    fn extend_mut_async_at<R, ER: IntoExtendMutReturn<Self::Extended, R>>(
+        self,
+        f: impl AsyncFnOnce(Self::Extended) -> ER,
+        location: &'static Location<'static>,
+    ) -> impl Future<Output = R> {
+        unsafe { self.extend_mut_async_unchecked_at(f, location) }
+    }
+    #[cfg(feature = "async")]
+    #[inline(always)]
+    #[track_caller]
+    unsafe fn extend_mut_async_unchecked<R, ER: IntoExtendMutReturn<Self::Extended, R>>(
+        self,
+        f: impl AsyncFnOnce(Self::Extended) -> ER,
+    ) -> impl Future<Output = R> {
+        unsafe { self.extend_mut_async_unchecked_at(f, Location::caller()) }
+    }
+    #[cfg(feature = "async")]
+    #[inline(always)]
+    unsafe fn extend_mut_async_unchecked_at<R, ER: IntoExtendMutReturn<Self::Extended, R>>(
+        self,
+        f: impl AsyncFnOnce(Self::Extended) -> ER,
+        location: &'static Location<'static>,
+    ) -> impl Future<Output = R> {
+        ReclaimFuture::<H, _, _, R, ER> {
+            raw: self.into_raw_at(location, Internal),
+            f: Some(f),
+            future: None,
+            location,
+            ready: false,
+            marker: PhantomData,
+            pinned: PhantomPinned,
+        }
     }
 }
 
-impl_extend_mut_many!(T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12, T13,);
+#[cfg(feature = "async")]
+pin_project_lite::pin_project! {
+    /// Future behind [`ExtendMut::extend_mut_async_unchecked`] for every [`Reclaim`] handle. Lends
+    /// the whole handle on the first poll, and dropping it before the first poll lends
+    /// nothing. The future is `!Unpin`, so `raw` does not move once it is pinned and lent.
+    struct ReclaimFuture<'b, H: Reclaim<'b>, F, Fut, R, ER> {
+        raw: H::Raw,
+        f: Option<F>,
+        #[pin]
+        future: Option<Fut>,
+        location: &'static Location<'static>,
+        ready: bool,
+        marker: PhantomData<fn() -> (R, ER)>,
+        // `raw` must not move once the handle is lent, even if `Fut` is `Unpin`.
+        #[pin]
+        pinned: PhantomPinned,
+    }
+
+    impl<'b, H: Reclaim<'b>, F, Fut, R, ER> PinnedDrop for ReclaimFuture<'b, H, F, Fut, R, ER> {
+        fn drop(this: Pin<&mut Self>) {
+            let this = this.project();
+            if this.f.is_none() && !*this.ready {
+                abort_no_unwind(ExtendMutError::DroppedBeforeReady {
+                    location: this.location,
+                    type_name: H::type_name(Internal),
+                });
+            }
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl<'b, H, F, R, ER> Future for ReclaimFuture<'b, H, F, F::CallOnceFuture, R, ER>
+where
+    H: Reclaim<'b>,
+    F: AsyncFnOnce(H::Extended) -> ER,
+    ER: IntoExtendMutReturn<H::Extended, R>,
+{
+    type Output = R;
+
+    #[inline(always)]
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<R> {
+        let mut this = self.project();
+
+        if *this.ready {
+            return Poll::Pending;
+        }
+
+        // Lent before `f` is taken and outside the guard, so a panic here (such as
+        // `ExtendMutError::ZeroSized`) unwinds normally and leaves nothing lent out.
+        let extended = match this.f.is_some() {
+            true => Some(unsafe { H::extend(this.raw) }),
+            false => None,
+        };
+        let unwound = ExtendMutError::Unwound {
+            location: this.location,
+            type_name: H::type_name(Internal),
+        };
+        let poll = abort_on_unwind(
+            #[inline(always)]
+            || {
+                if let (Some(f), Some(extended)) = (this.f.take(), extended) {
+                    this.future.set(Some(f(extended)));
+                }
+                match this.future.as_mut().as_pin_mut() {
+                    Some(future) => future.poll(cx),
+                    None => unreachable!(),
+                }
+            },
+            unwound,
+        );
+
+        match poll {
+            Poll::Ready(ret) => {
+                let (extended, next) = ret.into_extend_mut_return();
+
+                if let Err(type_name) = H::check_reclaimed(this.raw, extended, Internal) {
+                    abort_no_unwind(ExtendMutError::PointerChanged {
+                        location: this.location,
+                        type_name,
+                    });
+                }
+
+                *this.ready = true;
+                Poll::Ready(next)
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
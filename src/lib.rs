@@ -1,5 +1,4 @@
 #![cfg_attr(all(not(test), not(feature = "std")), no_std)]
-#![recursion_limit = "512"]
 #![cfg_attr(feature = "async", feature(async_fn_traits))]
 
 /*!
@@ -12,27 +11,60 @@ with a blocking closure.
 [`extend_mut_async`] is similar to [`extend_mut`], but it is async and requires
 a linear type be safe - but Rust does not have linear types yet, so it is unsafe.
 
+[`ExtendMut`] extends several references at once: tuples and arrays of them, and
+with the `alloc` feature (enabled by `std`) also `Vec<&mut T>` and `Box<[&mut T]>`.
+
+When a closure breaks the contract (returns a different reference, unwinds, or the
+future is dropped early), the process is aborted. Under `std` you can observe that
+with `set_abort_hook`; under `no_std` enable the `extern-abort-hook` feature and
+define the hook with `abort_hook!`. With both, the `abort_hook!` hook runs first.
+
 */
 
-use core::ptr;
+#[cfg(feature = "alloc")]
+extern crate alloc;
 
-#[cfg(feature = "async")]
-use core::{
-    future::Future,
-    marker::PhantomData,
-    pin::Pin,
-    task::{Context, Poll},
+use core::{panic::Location, pin::Pin};
+
+#[cfg(feature = "stable-async")]
+use {
+    aborts::{abort_no_unwind, abort_on_unwind},
+    core::{
+        future::Future,
+        marker::{PhantomData, PhantomPinned},
+        task::{Context, Poll},
+    },
 };
 
-use aborts::{abort_no_unwind, abort_on_unwind};
+pub use aborts::AbortInfo;
+#[cfg(feature = "std")]
+pub use aborts::{AbortHook, set_abort_hook, take_abort_hook};
+pub use error::ExtendMutError;
+#[cfg(feature = "alloc")]
+pub use maps::extend_mut_btree_map_values;
+#[cfg(feature = "std")]
+pub use maps::extend_mut_hash_map_values;
+pub use reclaim::{RawMut, Reclaim};
+pub use split::{extend_mut_chunks, extend_mut_split};
 
 mod aborts;
+mod error;
+mod identity;
 mod impls;
+mod macros;
+#[cfg(feature = "alloc")]
+mod maps;
+#[cfg(all(feature = "std", feature = "stable-async"))]
+mod park;
+mod reclaim;
+mod split;
 
 /// Trait designed to allow extending the lifetime of a mutable reference.
-/// It does not currently support async, contributions are welcome.
+/// With the `async` feature it also lends asynchronously, through
+/// `extend_mut_async_unchecked`, or `extend_mut_async` with `assume-non-forget`.
 /// # Examples
 /// ```
+/// # #[cfg(feature = "extern-abort-hook")] extend_mut::abort_hook!(|_| {});
 /// use extend_mut::ExtendMut;
 ///
 /// let (mut t1, mut t2) = (1, 2);
@@ -42,15 +74,118 @@ mod impls;
 /// ````
 pub trait ExtendMut<'b>: Sized {
     type Extended;
+    #[track_caller]
     fn extend_mut<R, ER: IntoExtendMutReturn<Self::Extended, R>>(
         self,
         f: impl FnOnce(Self::Extended) -> ER,
     ) -> R;
-    #[cfg(feature = "assume-non-forget")]
+    #[cfg(all(feature = "async", feature = "assume-non-forget"))]
+    #[track_caller]
     fn extend_mut_async<R, ER: IntoExtendMutReturn<Self::Extended, R>>(
         self,
         f: impl AsyncFnOnce(Self::Extended) -> ER,
     ) -> impl Future<Output = R>;
+    /// Async version of [`ExtendMut::extend_mut`], like [`extend_mut_async`]. Unlike
+    /// `ExtendMut::extend_mut_async`, it is available without the `assume-non-forget`
+    /// feature.
+    ///
+    /// # Safety
+    /// Same as [`extend_mut_async`]: the returned future must not be leaked once it
+    /// is polled.
+    #[cfg(feature = "async")]
+    #[track_caller]
+    unsafe fn extend_mut_async_unchecked<R, ER: IntoExtendMutReturn<Self::Extended, R>>(
+        self,
+        f: impl AsyncFnOnce(Self::Extended) -> ER,
+    ) -> impl Future<Output = R>;
+
+    /// Fallible version of [`ExtendMut::extend_mut`], see [`try_extend_mut`].
+    #[track_caller]
+    fn try_extend_mut<R, E, ER, EE>(
+        self,
+        f: impl FnOnce(Self::Extended) -> Result<ER, EE>,
+    ) -> Result<R, E>
+    where
+        ER: IntoExtendMutReturn<Self::Extended, R>,
+        EE: IntoExtendMutReturn<Self::Extended, E>,
+    {
+        self.extend_mut_at(
+            #[inline(always)]
+            |x| match f(x) {
+                Ok(ret) => {
+                    let (x, r) = ret.into_extend_mut_return();
+                    (x, Ok(r))
+                }
+                Err(ret) => {
+                    let (x, e) = ret.into_extend_mut_return();
+                    (x, Err(e))
+                }
+            },
+            Location::caller(),
+        )
+    }
+
+    /// Same as [`ExtendMut::extend_mut`], but without the unwind guard and the check of
+    /// what `f` returns, see [`extend_mut_unchecked`].
+    ///
+    /// # Safety
+    /// Same as [`extend_mut_unchecked`].
+    #[track_caller]
+    unsafe fn extend_mut_unchecked<R, ER: IntoExtendMutReturn<Self::Extended, R>>(
+        self,
+        f: impl FnOnce(Self::Extended) -> ER,
+    ) -> R {
+        unsafe { self.extend_mut_unchecked_at(f, Location::caller()) }
+    }
+
+    /// Same as [`ExtendMut::extend_mut`], but reports `location` on abort. Used to keep
+    /// the original call site when one impl is built from another.
+    #[doc(hidden)]
+    fn extend_mut_at<R, ER: IntoExtendMutReturn<Self::Extended, R>>(
+        self,
+        f: impl FnOnce(Self::Extended) -> ER,
+        location: &'static Location<'static>,
+    ) -> R {
+        let _ = location;
+        self.extend_mut(f)
+    }
+    /// Same as [`ExtendMut::extend_mut_unchecked`], but reports `location` on abort in
+    /// debug builds.
+    ///
+    /// # Safety
+    /// Same as [`extend_mut_unchecked`].
+    #[doc(hidden)]
+    unsafe fn extend_mut_unchecked_at<R, ER: IntoExtendMutReturn<Self::Extended, R>>(
+        self,
+        f: impl FnOnce(Self::Extended) -> ER,
+        location: &'static Location<'static>,
+    ) -> R {
+        self.extend_mut_at(f, location)
+    }
+    #[cfg(all(feature = "async", feature = "assume-non-forget"))]
+    #[doc(hidden)]
+    fn extend_mut_async_at<R, ER: IntoExtendMutReturn<Self::Extended, R>>(
+        self,
+        f: impl AsyncFnOnce(Self::Extended) -> ER,
+        location: &'static Location<'static>,
+    ) -> impl Future<Output = R> {
+        let _ = location;
+        self.extend_mut_async(f)
+    }
+    /// Same as [`ExtendMut::extend_mut_async_unchecked`], but reports `location` on abort.
+    ///
+    /// # Safety
+    /// Same as [`extend_mut_async`].
+    #[cfg(feature = "async")]
+    #[doc(hidden)]
+    unsafe fn extend_mut_async_unchecked_at<R, ER: IntoExtendMutReturn<Self::Extended, R>>(
+        self,
+        f: impl AsyncFnOnce(Self::Extended) -> ER,
+        location: &'static Location<'static>,
+    ) -> impl Future<Output = R> {
+        let _ = location;
+        unsafe { self.extend_mut_async_unchecked(f) }
+    }
 }
 
 /// Trait designed to allow returning both `&mut T` and `(&mut T, R)`, as well
@@ -61,6 +196,40 @@ pub unsafe trait IntoExtendMutReturn<T, R> {
     fn into_extend_mut_return(self) -> (T, R);
 }
 
+/// Gives the references back with no result. Unlike returning them directly, this has
+/// exactly one [`IntoExtendMutReturn`] impl, so it never needs type annotations.
+///
+/// ```
+/// # #[cfg(feature = "extern-abort-hook")] extend_mut::abort_hook!(|_| {});
+/// use extend_mut::{Back, ExtendMut};
+///
+/// fn lend_all<E: for<'b> ExtendMut<'b>>(handles: E) {
+///     // `|it| it` would ask for `E::Extended` to be a single `Reclaim` handle.
+///     handles.extend_mut(Back)
+/// }
+///
+/// let (mut a, mut b) = (1, 2);
+/// lend_all((&mut a, &mut b));
+/// ```
+pub struct Back<T>(pub T);
+
+/// Gives the references back together with a result. Same as returning `(refs, r)`,
+/// but spelled out, which helps when `refs` is only known through a generic parameter.
+///
+/// ```
+/// # #[cfg(feature = "extern-abort-hook")] extend_mut::abort_hook!(|_| {});
+/// use extend_mut::{ExtendMut, With};
+///
+/// fn count<E: for<'b> ExtendMut<'b>>(handles: E, leaked: &'static mut u32) -> &'static mut u32 {
+///     handles.extend_mut(|it| With(it, leaked))
+/// }
+///
+/// let (mut a, mut b) = (1, 2);
+/// let leaked = count((&mut a, &mut b), Box::leak(Box::new(7)));
+/// assert_eq!(*leaked, 7);
+/// ```
+pub struct With<T, R>(pub T, pub R);
+
 #[allow(dead_code)]
 fn extend_mut_proof_for_smaller<'a: 'b, 'b, T: 'b, R>(
     mut_ref: &'a mut T,
@@ -80,7 +249,8 @@ fn extend_mut_proof_for_smaller<'a: 'b, 'b, T: 'b, R>(
 //         else `f` must return `&'b mut T`
 //           if `T` is not zst then returned `&'b mut T` is different from the one it stored.
 //               we verify it by runtime assertion.
-//           if `T` is zst then we remove this case by compile-time assertion.
+//           if `T` is zst then we lend it at the address of a `ZstToken` local, which only
+//               the reference we gave out can point into, and verify it the same way.
 //     else we know that `f` did not store the reference we gave it, so it is sound.
 
 /// Extends the lifetime of a mutable reference. `f` must return the same reference
@@ -90,7 +260,16 @@ fn extend_mut_proof_for_smaller<'a: 'b, 'b, T: 'b, R>(
 ///
 /// You can return either `&'b mut T` or `(&'b mut T, R)` from `f`.
 ///
+/// Zero-sized values (such as empty slices) are supported, but `f` will observe them
+/// at a different address than `mut_ref`. Zero-sized values aligned to more than 16
+/// bytes are not supported and cause a panic before `f` is called.
+///
+/// A trait object must come back with the vtable it was lent with, so give back the
+/// reference `f` received: one rebuilt from the concrete type inside `f` may have
+/// another vtable and abort.
+///
 /// ```
+/// # #[cfg(feature = "extern-abort-hook")] extend_mut::abort_hook!(|_| {});
 /// use extend_mut::extend_mut;
 ///
 /// let mut x = 5;
@@ -112,82 +291,318 @@ fn extend_mut_proof_for_smaller<'a: 'b, 'b, T: 'b, R>(
 /// assert_eq!(x, 8);
 /// ```
 #[inline(always)]
+#[track_caller]
 pub fn extend_mut<'a, 'b, T: ?Sized + 'b, F, R, ExtR>(mut_ref: &'a mut T, f: F) -> R
 where
     F: FnOnce(&'b mut T) -> ExtR,
     ExtR: IntoExtendMutReturn<&'b mut T, R>,
 {
-    assert!(size_of_val::<T>(&*mut_ref) != 0);
+    extend_mut_at(mut_ref, f, Location::caller())
+}
 
-    let ptr = ptr::from_mut(mut_ref);
-    let ret = abort_on_unwind(
-        #[inline(always)]
-        move || f(unsafe { &mut *ptr }),
-    );
-    let (extended, next) = ret.into_extend_mut_return();
+#[inline(always)]
+pub(crate) fn extend_mut_at<'a, 'b, T: ?Sized + 'b, F, R, ExtR>(
+    mut_ref: &'a mut T,
+    f: F,
+    location: &'static Location<'static>,
+) -> R
+where
+    F: FnOnce(&'b mut T) -> ExtR,
+    ExtR: IntoExtendMutReturn<&'b mut T, R>,
+{
+    ExtendMut::extend_mut_at(mut_ref, f, location)
+}
 
-    // We are checking both address and metadata, because for slices one might make a split.
-    if !core::ptr::eq(ptr, ptr::from_mut(extended)) {
-        abort_no_unwind("ExtendMut: Pointer changed");
-    }
+/// Same as [`extend_mut`], but skips the unwind guard and the check that `f` returned
+/// the same reference, for hot paths where they show up in profiles.
+///
+/// Builds with `debug_assertions` keep both, so a broken contract still aborts there.
+///
+/// # Safety
+///
+/// `f` must not unwind, and must return the reference it was given. Nothing derived
+/// from that reference may be kept anywhere once `f` returns.
+///
+/// ```
+/// # #[cfg(feature = "extern-abort-hook")] extend_mut::abort_hook!(|_| {});
+/// use extend_mut::extend_mut_unchecked;
+///
+/// fn bump(counter: &'static mut u64) -> &'static mut u64 {
+///     *counter += 1;
+///     counter
+/// }
+///
+/// let mut packets = 0;
+/// for _ in 0..3 {
+///     // SAFETY: `bump` returns the counter it gets and does not panic.
+///     unsafe { extend_mut_unchecked(&mut packets, bump) };
+/// }
+/// assert_eq!(packets, 3);
+/// ```
+#[inline(always)]
+#[track_caller]
+pub unsafe fn extend_mut_unchecked<'a, 'b, T: ?Sized + 'b, F, R, ExtR>(
+    mut_ref: &'a mut T,
+    f: F,
+) -> R
+where
+    F: FnOnce(&'b mut T) -> ExtR,
+    ExtR: IntoExtendMutReturn<&'b mut T, R>,
+{
+    unsafe { mut_ref.extend_mut_unchecked(f) }
+}
+
+/// Fallible version of [`extend_mut`]. `f` returns `Ok(ref)` or `Ok((ref, r))` on
+/// success and `Err((ref, e))` on failure, and the reference is checked in both cases
+/// before `Ok(r)` or `Err(e)` is returned.
+///
+/// The reference cannot be handed back through `?`, use [`try_back!`] instead.
+///
+/// ```
+/// # #[cfg(feature = "extern-abort-hook")] extend_mut::abort_hook!(|_| {});
+/// use core::str::Utf8Error;
+/// use extend_mut::{try_back, try_extend_mut};
+///
+/// fn keep(buf: &'static mut [u8]) -> &'static mut [u8] {
+///     buf
+/// }
+///
+/// let mut buf = *b"abcd";
+/// let len: Result<usize, Utf8Error> = try_extend_mut(&mut buf[..], |buf| {
+///     let len = try_back!(buf, core::str::from_utf8(buf)).len();
+///     Ok((keep(buf), len))
+/// });
+/// assert_eq!(len, Ok(4));
+/// ```
+#[inline(always)]
+#[track_caller]
+pub fn try_extend_mut<'a, 'b, T: ?Sized + 'b, F, R, E, ExtR, ExtE>(
+    mut_ref: &'a mut T,
+    f: F,
+) -> Result<R, E>
+where
+    F: FnOnce(&'b mut T) -> Result<ExtR, ExtE>,
+    ExtR: IntoExtendMutReturn<&'b mut T, R>,
+    ExtE: IntoExtendMutReturn<&'b mut T, E>,
+{
+    mut_ref.try_extend_mut(f)
+}
+
+/// Same as [`extend_mut`], but for pinned references: `f` gets `Pin<&'b mut T>` and
+/// must return it, either alone or as `(Pin<&'b mut T>, R)`.
+///
+/// Zero-sized values cannot be lent at their own address (see [`extend_mut`]), which
+/// would break the pinning guarantee, so they cause a panic before `f` is called.
+///
+/// ```
+/// # #[cfg(feature = "extern-abort-hook")] extend_mut::abort_hook!(|_| {});
+/// use core::pin::{Pin, pin};
+/// use extend_mut::extend_mut_pin;
+///
+/// fn register<F: Future>(fut: Pin<&'static mut F>) -> Pin<&'static mut F> {
+///     fut
+/// }
+///
+/// let mut fut = pin!(async { 42 });
+/// extend_mut_pin(fut.as_mut(), register);
+/// let ready = extend_mut_pin(fut.as_mut(), |fut| (fut, true));
+/// assert!(ready);
+/// ```
+#[inline(always)]
+#[track_caller]
+pub fn extend_mut_pin<'a, 'b, T: ?Sized + 'b, F, R, ExtR>(pinned: Pin<&'a mut T>, f: F) -> R
+where
+    F: FnOnce(Pin<&'b mut T>) -> ExtR,
+    ExtR: IntoExtendMutReturn<Pin<&'b mut T>, R>,
+{
+    extend_mut_pin_at(pinned, f, Location::caller())
+}
+
+#[inline(always)]
+pub(crate) fn extend_mut_pin_at<'a, 'b, T: ?Sized + 'b, F, R, ExtR>(
+    pinned: Pin<&'a mut T>,
+    f: F,
+    location: &'static Location<'static>,
+) -> R
+where
+    F: FnOnce(Pin<&'b mut T>) -> ExtR,
+    ExtR: IntoExtendMutReturn<Pin<&'b mut T>, R>,
+{
+    ExtendMut::extend_mut_at(pinned, f, location)
+}
+
+/// Extends the target of `guard`, keeping the guard alive for the whole call, and gives
+/// the guard back afterwards. Works with any [`DerefMut`](core::ops::DerefMut) guard,
+/// such as `MutexGuard`, `RwLockWriteGuard` or [`RefMut`](core::cell::RefMut).
+///
+/// Same rules as [`extend_mut`] apply to `f`. To drop the guard right after the call,
+/// use [`ExtendMut`] on the guard itself.
+///
+/// ```
+/// # #[cfg(feature = "extern-abort-hook")] extend_mut::abort_hook!(|_| {});
+/// use std::sync::Mutex;
+/// use extend_mut::extend_mut_guard;
+///
+/// fn bump(x: &'static mut i32) -> &'static mut i32 {
+///     *x += 1;
+///     x
+/// }
+///
+/// let mutex = Mutex::new(1);
+/// let (guard, ()) = extend_mut_guard(mutex.lock().unwrap(), bump);
+/// assert_eq!(*guard, 2);
+/// ```
+#[inline(always)]
+#[track_caller]
+pub fn extend_mut_guard<'b, G, F, R, ExtR>(mut guard: G, f: F) -> (G, R)
+where
+    G: core::ops::DerefMut<Target: 'b>,
+    F: FnOnce(&'b mut G::Target) -> ExtR,
+    ExtR: IntoExtendMutReturn<&'b mut G::Target, R>,
+{
+    let r = extend_mut_at(&mut *guard, f, Location::caller());
+    (guard, r)
+}
 
-    next
+/// Closure lent the reference by [`ExtendMutFuture`] on its first poll. Implemented
+/// for closures returning a future, and for async closures wrapped in `AsyncLend`.
+///
+/// This is the fourth type parameter of [`ExtendMutFuture`], which used to be the
+/// future itself.
+#[cfg(feature = "stable-async")]
+pub trait LendFn<'b, T: ?Sized> {
+    /// Future returned by the closure.
+    type Future: Future;
+    /// Calls the closure with the lent reference.
+    fn call(self, x: &'b mut T) -> Self::Future;
+}
+
+#[cfg(feature = "stable-async")]
+impl<'b, T: ?Sized + 'b, F, Fut: Future> LendFn<'b, T> for F
+where
+    F: FnOnce(&'b mut T) -> Fut,
+{
+    type Future = Fut;
+    #[inline(always)]
+    fn call(self, x: &'b mut T) -> Fut {
+        self(x)
+    }
 }
 
+/// Async closure passed to `extend_mut_async`, see [`LendFn`].
+#[cfg(feature = "async")]
+pub struct AsyncLend<F>(F);
+
 #[cfg(feature = "async")]
+impl<'b, T: ?Sized + 'b, F: AsyncFnOnce(&'b mut T) -> O, O> LendFn<'b, T> for AsyncLend<F> {
+    type Future = F::CallOnceFuture;
+    #[inline(always)]
+    fn call(self, x: &'b mut T) -> Self::Future {
+        (self.0)(x)
+    }
+}
+
+#[cfg(feature = "stable-async")]
 pin_project_lite::pin_project! {
-    /// Future returned by returned by [extend_mut_async].
+    /// Future returned by [extend_mut_future] and `extend_mut_async`.
     /// Consult it's documentation for more information and safety requirements.
     /// `'a` is to hold smaller borrow.
     /// `'b` is to enforce that larger borrow is returned.
-    pub struct ExtendMutFuture<'a, 'b, T: ?Sized, Fut, R, ExtR> {
-        ptr: *mut T,
-        marker: PhantomData<(&'a mut T, &'b mut T, R, ExtR)>,
+    ///
+    /// The reference is only lent to `f` on the first poll, once the future is pinned,
+    /// so zero-sized values can be lent through a token inside the future. Dropping it
+    /// before the first poll lends nothing.
+    ///
+    /// The future is [`Send`] when `T`, `F` and the future of `F` are:
+    ///
+    /// ```compile_fail,E0277
+    /// # use extend_mut::ExtendMutFuture;
+    /// use std::{future::Ready, rc::Rc};
+    /// fn assert_send<T: Send>() {}
+    /// type F = fn(&'static mut Rc<i32>) -> Ready<&'static mut Rc<i32>>;
+    /// assert_send::<ExtendMutFuture<'static, 'static, Rc<i32>, F, (), ()>>();
+    /// ```
+    ///
+    /// ```compile_fail,E0277
+    /// # use extend_mut::ExtendMutFuture;
+    /// use std::{future::Ready, rc::Rc};
+    /// fn assert_send<T: Send>() {}
+    /// type F = fn(&'static mut i32) -> Ready<(&'static mut i32, Rc<i32>)>;
+    /// assert_send::<ExtendMutFuture<'static, 'static, i32, F, Rc<i32>, ()>>();
+    /// ```
+    pub struct ExtendMutFuture<'a, 'b, T: ?Sized, F, R, ExtR>
+    where
+        F: LendFn<'b, T>,
+    {
+        raw: RawMut<T>,
+        // `R` and `ExtR` are never stored, so they don't affect auto traits.
+        marker: PhantomData<(&'a mut T, &'b mut T, fn() -> (R, ExtR))>,
+        f: Option<F>,
         #[pin]
-        future: Fut,
-        // Instead of having that bool, we might make `ptr` null.
+        future: Option<F::Future>,
         ready: bool,
+        // `raw` must not move once the reference is lent.
+        #[pin]
+        pinned: PhantomPinned,
     }
 
-    impl<'a, 'b, T: ?Sized, Fut, R, ExtR> PinnedDrop for ExtendMutFuture<'a, 'b, T, Fut, R, ExtR> {
+    impl<'a, 'b, T: ?Sized, F, R, ExtR> PinnedDrop for ExtendMutFuture<'a, 'b, T, F, R, ExtR>
+    where
+        F: LendFn<'b, T>,
+    {
         fn drop(this: Pin<&mut Self>) {
-            if !*this.project().ready {
-                abort_no_unwind("Cannot drop ExtendMutFuture before it yields Poll::Ready");
+            let this = this.project();
+            if this.f.is_none() && !*this.ready {
+                abort_no_unwind(ExtendMutError::dropped_before_ready::<T>(this.raw.location()));
             }
         }
     }
 }
 
-#[cfg(feature = "async")]
-impl<'a, 'b, T, Fut, R, ExdR> Future for ExtendMutFuture<'a, 'b, T, Fut, R, ExdR>
+#[cfg(feature = "stable-async")]
+impl<'a, 'b, T, F, R, ExdR> Future for ExtendMutFuture<'a, 'b, T, F, R, ExdR>
 where
-    T: ?Sized,
+    T: ?Sized + 'b,
     ExdR: IntoExtendMutReturn<&'b mut T, R>,
-    Fut: Future<Output = ExdR>,
+    F: LendFn<'b, T, Future: Future<Output = ExdR>>,
 {
     type Output = R;
 
     #[inline(always)]
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        let this = self.project();
-        let ptr = *this.ptr;
+        let mut this = self.project();
 
         if *this.ready {
             return Poll::Pending;
         }
 
+        let location = this.raw.location();
+        // Lent before `f` is taken, so a panic here leaves nothing lent out.
+        let lent = match this.f.is_some() {
+            true => Some(unsafe { &mut *this.raw.lend() }),
+            false => None,
+        };
         match abort_on_unwind(
             #[inline(always)]
-            move || this.future.poll(cx),
+            || {
+                if let (Some(f), Some(lent)) = (this.f.take(), lent) {
+                    this.future.set(Some(f.call(lent)));
+                }
+                match this.future.as_mut().as_pin_mut() {
+                    Some(future) => future.poll(cx),
+                    None => unreachable!(),
+                }
+            },
+            ExtendMutError::unwound::<T>(location),
         ) {
             Poll::Ready(ret) => {
                 let (extended, ret) = ret.into_extend_mut_return();
 
-                if core::ptr::eq(ptr, ptr::from_mut(extended)) {
+                if this.raw.matches(extended) {
                     *this.ready = true;
                     Poll::Ready(ret)
                 } else {
-                    abort_no_unwind("ExtendMut: Pointer changed")
+                    abort_no_unwind(ExtendMutError::pointer_changed::<T>(location))
                 }
             }
             Poll::Pending => Poll::Pending,
@@ -195,9 +610,173 @@ where
     }
 }
 
-/// Async version of [`extend_mut`]. You should not drop the future returned by [`extend_mut_async`]
-/// until it yields [`Poll::Ready`] - if you do, it will abort the process. This function is *not*
-/// cancel-safe.
+#[cfg(all(feature = "std", feature = "stable-async"))]
+impl<'a, 'b, T: ?Sized + 'b, F: LendFn<'b, T>, R, ExtR> ExtendMutFuture<'a, 'b, T, F, R, ExtR> {
+    /// Whether the reference is lent out and not back yet.
+    #[inline(always)]
+    fn is_lent(self: Pin<&mut Self>) -> bool {
+        // Not `self.ready`: a shared reference to the whole future would alias the
+        // inner one, which may point into itself.
+        let this = self.project();
+        this.f.is_none() && !*this.ready
+    }
+}
+
+#[cfg(all(feature = "std", feature = "stable-async"))]
+impl<'a, 'b, T, F, R, ExdR> ExtendMutFuture<'a, 'b, T, F, R, ExdR>
+where
+    T: ?Sized + 'b,
+    ExdR: IntoExtendMutReturn<&'b mut T, R>,
+    F: LendFn<'b, T, Future: Future<Output = ExdR>>,
+{
+    /// Opts into scoped-thread semantics: if the returned future is dropped before it
+    /// yields [`Poll::Ready`], the drop blocks the current thread and drives the inner
+    /// future to completion instead of aborting. The returned reference is still checked.
+    ///
+    /// This makes cancellation, e.g. a lost branch of `select!`, wait for the future to
+    /// finish. If that future waits on something only the current thread can do, such
+    /// as a task of a single-threaded executor, the drop never returns. Leaking the
+    /// future is just as unsound as before.
+    ///
+    /// ```
+    /// # #[cfg(feature = "extern-abort-hook")] extend_mut::abort_hook!(|_| {});
+    /// use core::{future::Future, pin::pin, task::{Context, Poll, Waker}};
+    /// use extend_mut::extend_mut_future;
+    ///
+    /// let mut x = 1;
+    /// {
+    ///     let fut = unsafe {
+    ///         extend_mut_future(&mut x, |x| async move {
+    ///             let mut yielded = false;
+    ///             core::future::poll_fn(|cx| match core::mem::replace(&mut yielded, true) {
+    ///                 true => Poll::Ready(()),
+    ///                 false => {
+    ///                     cx.waker().wake_by_ref();
+    ///                     Poll::Pending
+    ///                 }
+    ///             })
+    ///             .await;
+    ///             *x += 1;
+    ///             x
+    ///         })
+    ///     };
+    ///     let mut fut = pin!(fut.block_on_drop());
+    ///     let cx = &mut Context::from_waker(Waker::noop());
+    ///     assert!(fut.as_mut().poll(cx).is_pending());
+    ///     // Dropped while pending: waits for `x` to come back instead of aborting.
+    /// }
+    /// assert_eq!(x, 2);
+    /// ```
+    #[inline(always)]
+    pub fn block_on_drop(self) -> BlockOnDrop<'a, 'b, T, F, R, ExdR> {
+        BlockOnDrop { inner: self }
+    }
+}
+
+#[cfg(all(feature = "std", feature = "stable-async"))]
+pin_project_lite::pin_project! {
+    /// [`ExtendMutFuture`] that blocks on drop instead of aborting, returned by
+    /// [`ExtendMutFuture::block_on_drop`].
+    pub struct BlockOnDrop<'a, 'b, T: ?Sized, F, R, ExtR>
+    where
+        ExtR: IntoExtendMutReturn<&'b mut T, R>,
+        F: LendFn<'b, T, Future: Future<Output = ExtR>>,
+    {
+        #[pin]
+        inner: ExtendMutFuture<'a, 'b, T, F, R, ExtR>,
+    }
+
+    impl<'a, 'b, T: ?Sized, F, R, ExtR> PinnedDrop for BlockOnDrop<'a, 'b, T, F, R, ExtR>
+    where
+        ExtR: IntoExtendMutReturn<&'b mut T, R>,
+        F: LendFn<'b, T, Future: Future<Output = ExtR>>,
+    {
+        fn drop(this: Pin<&mut Self>) {
+            let mut inner = this.project().inner;
+            if inner.as_mut().is_lent() {
+                park::block_on(inner);
+            }
+        }
+    }
+}
+
+#[cfg(all(feature = "std", feature = "stable-async"))]
+impl<'a, 'b, T, F, R, ExdR> Future for BlockOnDrop<'a, 'b, T, F, R, ExdR>
+where
+    T: ?Sized + 'b,
+    ExdR: IntoExtendMutReturn<&'b mut T, R>,
+    F: LendFn<'b, T, Future: Future<Output = ExdR>>,
+{
+    type Output = R;
+
+    #[inline(always)]
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.project().inner.poll(cx)
+    }
+}
+
+/// Same as `extend_mut_async`, but `f` is a closure returning a future instead of an
+/// async closure, so it works on stable Rust. Same rules and safety requirements apply:
+/// once polled, the returned future must not be dropped or leaked before it yields
+/// [`Poll::Ready`].
+///
+/// ```
+/// # #[cfg(feature = "extern-abort-hook")] extend_mut::abort_hook!(|_| {});
+/// use extend_mut::extend_mut_future;
+///
+/// async fn want_static(x: &'static mut i32) -> &'static mut i32 {
+///     *x += 1;
+///     x
+/// }
+///
+/// let mut x = 1;
+/// {
+///     let fut = core::pin::pin!(unsafe { extend_mut_future(&mut x, want_static) });
+///     let cx = &mut core::task::Context::from_waker(core::task::Waker::noop());
+///     assert!(fut.poll(cx).is_ready());
+/// }
+/// assert_eq!(x, 2);
+/// ```
+///
+/// # Safety
+///
+/// Same as `extend_mut_async`.
+#[cfg(feature = "stable-async")]
+#[cfg(not(feature = "assume-non-forget"))]
+#[track_caller]
+pub unsafe fn extend_mut_future<'a, 'b, T: ?Sized + 'b, F, Fut, R, ExdR>(
+    mut_ref: &'a mut T,
+    f: F,
+) -> ExtendMutFuture<'a, 'b, T, F, R, ExdR>
+where
+    ExdR: IntoExtendMutReturn<&'b mut T, R>,
+    F: FnOnce(&'b mut T) -> Fut,
+    Fut: Future<Output = ExdR>,
+{
+    unsafe { extend_mut_future_inner(mut_ref, f, Location::caller()) }
+}
+
+/// Same as `extend_mut_async`, but `f` is a closure returning a future instead of an
+/// async closure, so it works on stable Rust.
+#[cfg(feature = "stable-async")]
+#[cfg(feature = "assume-non-forget")]
+#[track_caller]
+pub fn extend_mut_future<'a, 'b, T: ?Sized + 'b, F, Fut, R, ExdR>(
+    mut_ref: &'a mut T,
+    f: F,
+) -> ExtendMutFuture<'a, 'b, T, F, R, ExdR>
+where
+    ExdR: IntoExtendMutReturn<&'b mut T, R>,
+    F: FnOnce(&'b mut T) -> Fut,
+    Fut: Future<Output = ExdR>,
+{
+    unsafe { extend_mut_future_inner(mut_ref, f, Location::caller()) }
+}
+
+/// Async version of [`extend_mut`]. The reference is only lent to `f` on the first poll, so
+/// dropping the future returned by [`extend_mut_async`] before that lends nothing. Once polled,
+/// you should not drop it until it yields [`Poll::Ready`] - if you do, it will abort the process.
+/// This function is *not* cancel-safe.
 ///
 /// If polled after yielding [`Poll::Ready`], it will always return [`Poll::Pending`].
 ///
@@ -207,75 +786,188 @@ where
 ///
 /// Shortly - do not cancel returned future.
 ///
-/// You must not skip abortion on dropping the future returned by [`extend_mut_async`]
-/// by any means, including [forget](core::mem::forget), [`ManuallyDrop`](core::mem::ManuallyDrop) etc. Otherwise,
+/// Once the future returned by [`extend_mut_async`] has been polled, you must not skip abortion
+/// on dropping it by any means, including [forget](core::mem::forget), [`ManuallyDrop`](core::mem::ManuallyDrop) etc. Otherwise,
 /// borrow checker will allow you to use `mut_ref` while it might be used by `f`, which will
 /// be undefined behavior.
 #[cfg(feature = "async")]
 #[cfg(not(feature = "assume-non-forget"))]
+#[track_caller]
 pub unsafe fn extend_mut_async<'a, 'b, T: 'b, F, R, ExdR>(
     mut_ref: &'a mut T,
     f: F,
-) -> ExtendMutFuture<'a, 'b, T, F::CallOnceFuture, R, ExdR>
+) -> ExtendMutFuture<'a, 'b, T, AsyncLend<F>, R, ExdR>
 where
     ExdR: IntoExtendMutReturn<&'b mut T, R>,
     F: AsyncFnOnce(&'b mut T) -> ExdR,
 {
-    unsafe { extend_mut_async_inner(mut_ref, f) }
+    unsafe { extend_mut_async_inner(mut_ref, f, Location::caller()) }
 }
 
 /// Async version of [`extend_mut`].
 #[cfg(feature = "async")]
 #[cfg(feature = "assume-non-forget")]
+#[track_caller]
 pub fn extend_mut_async<'a, 'b, T: ?Sized + 'b, F, R, ExdR>(
     mut_ref: &'a mut T,
     f: F,
-) -> ExtendMutFuture<'a, 'b, T, F::CallOnceFuture, R, ExdR>
+) -> ExtendMutFuture<'a, 'b, T, AsyncLend<F>, R, ExdR>
 where
     ExdR: IntoExtendMutReturn<&'b mut T, R>,
     F: AsyncFnOnce(&'b mut T) -> ExdR,
 {
-    unsafe { extend_mut_async_inner(mut_ref, f) }
+    extend_mut_async_at(mut_ref, f, Location::caller())
 }
 
 #[cfg(feature = "async")]
-unsafe fn extend_mut_async_inner<'a, 'b, T: ?Sized + 'b, F, R, ExdR>(
+#[cfg(feature = "assume-non-forget")]
+#[inline(always)]
+pub(crate) fn extend_mut_async_at<'a, 'b, T: ?Sized + 'b, F, R, ExdR>(
     mut_ref: &'a mut T,
     f: F,
-) -> ExtendMutFuture<'a, 'b, T, F::CallOnceFuture, R, ExdR>
+    location: &'static Location<'static>,
+) -> ExtendMutFuture<'a, 'b, T, AsyncLend<F>, R, ExdR>
 where
     ExdR: IntoExtendMutReturn<&'b mut T, R>,
     F: AsyncFnOnce(&'b mut T) -> ExdR,
 {
-    assert!(size_of_val::<T>(&*mut_ref) != 0);
-
-    let ptr = ptr::from_mut(mut_ref);
-    let future = f(unsafe { &mut *ptr });
+    unsafe { extend_mut_async_inner(mut_ref, f, location) }
+}
 
-    ExtendMutFuture {
-        ptr,
-        marker: PhantomData,
-        future,
-        ready: false,
-    }
+/// Async version of [`extend_mut_pin`]. Same rules as [`extend_mut_async`] apply.
+///
+/// # Safety
+///
+/// Same as [`extend_mut_async`]: the returned future must not be leaked before it
+/// yields [`Poll::Ready`].
+#[cfg(feature = "async")]
+#[cfg(not(feature = "assume-non-forget"))]
+#[track_caller]
+pub unsafe fn extend_mut_pin_async<'a, 'b, T: ?Sized + 'b, F, R, ExdR>(
+    pinned: Pin<&'a mut T>,
+    f: F,
+) -> impl Future<Output = R>
+where
+    ExdR: IntoExtendMutReturn<Pin<&'b mut T>, R>,
+    F: AsyncFnOnce(Pin<&'b mut T>) -> ExdR,
+{
+    unsafe { extend_mut_pin_async_inner(pinned, f, Location::caller()) }
 }
 
-#[cfg(test)]
-mod test {
-    use super::*;
+/// Async version of [`extend_mut_pin`].
+#[cfg(feature = "async")]
+#[cfg(feature = "assume-non-forget")]
+#[track_caller]
+pub fn extend_mut_pin_async<'a, 'b, T: ?Sized + 'b, F, R, ExdR>(
+    pinned: Pin<&'a mut T>,
+    f: F,
+) -> impl Future<Output = R>
+where
+    ExdR: IntoExtendMutReturn<Pin<&'b mut T>, R>,
+    F: AsyncFnOnce(Pin<&'b mut T>) -> ExdR,
+{
+    extend_mut_pin_async_at(pinned, f, Location::caller())
+}
 
-    #[rustfmt::skip]
-    #[test]
-    fn test_sync_api() {
-        let (mut t1, mut t2, mut t3, mut t4) = (1, 2, 3, 4);
+#[cfg(feature = "async")]
+#[cfg(feature = "assume-non-forget")]
+#[inline(always)]
+pub(crate) fn extend_mut_pin_async_at<'a, 'b, T: ?Sized + 'b, F, R, ExdR>(
+    pinned: Pin<&'a mut T>,
+    f: F,
+    location: &'static Location<'static>,
+) -> impl Future<Output = R>
+where
+    ExdR: IntoExtendMutReturn<Pin<&'b mut T>, R>,
+    F: AsyncFnOnce(Pin<&'b mut T>) -> ExdR,
+{
+    unsafe { extend_mut_pin_async_inner(pinned, f, location) }
+}
 
-        let () = extend_mut(&mut t1, |t1: &'static mut u8| t1);
-        let "hi" = extend_mut(&mut t1, |t1| (t1, "hi")) else { panic!() };
-        let () = extend_mut(&mut t1, |t1| (t1, ()));
+#[cfg(feature = "async")]
+#[inline(always)]
+unsafe fn extend_mut_pin_async_inner<'a, 'b, T: ?Sized + 'b, F, R, ExdR>(
+    pinned: Pin<&'a mut T>,
+    f: F,
+    location: &'static Location<'static>,
+) -> impl Future<Output = R>
+where
+    ExdR: IntoExtendMutReturn<Pin<&'b mut T>, R>,
+    F: AsyncFnOnce(Pin<&'b mut T>) -> ExdR,
+{
+    // SAFETY: the value is never moved, `f` only ever observes it pinned.
+    let mut_ref = unsafe { Pin::get_unchecked_mut(pinned) };
+    // Zero-sized values are lent at another address, which would break pinning.
+    if size_of_val::<T>(mut_ref) == 0 {
+        panic!("{}", ExtendMutError::zero_sized::<T>(location));
+    }
+    let f = async move |x| {
+        let (x, r) = f(unsafe { Pin::new_unchecked(x) })
+            .await
+            .into_extend_mut_return();
+        (unsafe { Pin::into_inner_unchecked(x) }, r)
+    };
+    unsafe { extend_mut_async_inner(mut_ref, f, location) }
+}
 
-        let () = t1.extend_mut(|t1| t1);
-        let () = (&mut t1).extend_mut(|t1: &'static mut u8| t1);
-        let "hi" = t1.extend_mut(|t1| (t1, "hi")) else { panic!() };
+#[cfg(feature = "async")]
+#[inline(always)]
+unsafe fn extend_mut_async_inner<'a, 'b, T: ?Sized + 'b, F, R, ExdR>(
+    mut_ref: &'a mut T,
+    f: F,
+    location: &'static Location<'static>,
+) -> ExtendMutFuture<'a, 'b, T, AsyncLend<F>, R, ExdR>
+where
+    ExdR: IntoExtendMutReturn<&'b mut T, R>,
+    F: AsyncFnOnce(&'b mut T) -> ExdR,
+{
+    unsafe { extend_mut_future_inner(mut_ref, AsyncLend(f), location) }
+}
+
+#[cfg(feature = "stable-async")]
+unsafe fn extend_mut_future_inner<'a, 'b, T: ?Sized + 'b, F, R, ExdR>(
+    mut_ref: &'a mut T,
+    f: F,
+    location: &'static Location<'static>,
+) -> ExtendMutFuture<'a, 'b, T, F, R, ExdR>
+where
+    ExdR: IntoExtendMutReturn<&'b mut T, R>,
+    F: LendFn<'b, T, Future: Future<Output = ExdR>>,
+{
+    ExtendMutFuture {
+        raw: RawMut::new(mut_ref, location),
+        marker: PhantomData,
+        f: Some(f),
+        future: None,
+        ready: false,
+        pinned: PhantomPinned,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[cfg(feature = "extern-abort-hook")]
+    crate::abort_hook!(|_| {});
+
+    fn bump(x: &'static mut i32) -> &'static mut i32 {
+        *x += 1;
+        x
+    }
+
+    #[rustfmt::skip]
+    #[test]
+    fn test_sync_api() {
+        let (mut t1, mut t2, mut t3, mut t4) = (1, 2, 3, 4);
+
+        let () = extend_mut(&mut t1, |t1: &'static mut u8| t1);
+        let "hi" = extend_mut(&mut t1, |t1| (t1, "hi")) else { panic!() };
+        let () = extend_mut(&mut t1, |t1| (t1, ()));
+
+        let () = t1.extend_mut(|t1| t1);
+        let () = (&mut t1).extend_mut(|t1: &'static mut u8| t1);
+        let "hi" = t1.extend_mut(|t1| (t1, "hi")) else { panic!() };
         let "hi" = (&mut t1).extend_mut(|t1| (t1, "hi")) else { panic!() };
 
         let () = (t1, t2).extend_mut(|it: &'static mut (u8, u8)| it);
@@ -300,6 +992,8 @@ mod test {
     }
 
     #[test]
+    // Covers the closure form, not just passing `want_static` itself.
+    #[allow(clippy::redundant_closure)]
     fn test_extend_mut() {
         let mut x = 5;
 
@@ -325,6 +1019,502 @@ mod test {
         assert_eq!(hi, "hi");
     }
 
+    #[test]
+    fn test_extend_mut_zero_sized() {
+        let mut unit = ();
+        let mut empty: [u8; 0] = [];
+        let mut units = [(); 3];
+        let mut s = String::new();
+
+        let () = extend_mut(&mut unit, |u: &'static mut ()| u);
+        let len = extend_mut(&mut empty[..], |e: &'static mut [u8]| {
+            let len = e.len();
+            (e, len)
+        });
+        assert_eq!(len, 0);
+        let len = extend_mut(&mut units[..], |u| {
+            let len = u.len();
+            (u, len)
+        });
+        assert_eq!(len, 3);
+        let () = extend_mut(s.as_mut_str(), |s: &'static mut str| s);
+
+        let () = (&mut unit, &mut empty[..], &mut units[..]).extend_mut(|it| it);
+        let () = (&mut unit).extend_mut(|u| u);
+    }
+
+    #[test]
+    fn test_extend_mut_array() {
+        let mut channels = [[0u8; 4]; 3];
+        let mut units = [(); 2];
+
+        fn fill(buf: &'static mut [u8; 4], byte: u8) -> &'static mut [u8; 4] {
+            buf.fill(byte);
+            buf
+        }
+
+        let [a, b, c] = &mut channels;
+        let () = [a, b, c].extend_mut(|[a, b, c]: [&'static mut [u8; 4]; 3]| {
+            [fill(a, 1), fill(b, 2), fill(c, 3)]
+        });
+        assert_eq!(channels, [[1; 4], [2; 4], [3; 4]]);
+
+        let len = channels.each_mut().extend_mut(|bufs| {
+            let len = bufs.len();
+            (bufs, len)
+        });
+        assert_eq!(len, 3);
+
+        let [u1, u2] = &mut units;
+        let () = [u1, u2].extend_mut(|it| it);
+        let () = <[&mut u8; 0]>::extend_mut([], |it| it);
+        let () = ([&mut channels[0]], &mut units[0]).extend_mut(|it| it);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_extend_mut_vec() {
+        let mut buffers = vec![vec![0u8; 2]; 3];
+        let mut units = [(); 2];
+
+        fn push(buf: &'static mut Vec<u8>, byte: u8) -> &'static mut Vec<u8> {
+            buf.push(byte);
+            buf
+        }
+
+        let refs: Vec<&mut Vec<u8>> = buffers.iter_mut().collect();
+        let () = refs.extend_mut(|bufs: Vec<&'static mut Vec<u8>>| {
+            bufs.into_iter().map(|buf| push(buf, 1)).collect::<Vec<_>>()
+        });
+        assert_eq!(buffers, vec![vec![0, 0, 1]; 3]);
+
+        let refs: Box<[&mut Vec<u8>]> = buffers.iter_mut().collect();
+        let len = refs.extend_mut(|bufs| {
+            let len = bufs.len();
+            (bufs, len)
+        });
+        assert_eq!(len, 3);
+
+        let () = units.iter_mut().collect::<Vec<_>>().extend_mut(|it| it);
+        let () = Vec::<&mut u8>::new().extend_mut(|it| it);
+    }
+
+    #[test]
+    fn test_extend_mut_split() {
+        let mut buf = [0u8; 7];
+        let mut units = [(); 3];
+
+        fn fill(chunk: &'static mut [u8], byte: u8) -> &'static mut [u8] {
+            chunk.fill(byte);
+            chunk
+        }
+
+        let () = extend_mut_split(&mut buf, 3, |l: &'static mut [u8], r| {
+            (fill(l, 1), fill(r, 2))
+        });
+        assert_eq!(buf, [1, 1, 1, 2, 2, 2, 2]);
+        let lens = extend_mut_split(&mut buf, 0, |l, r| {
+            let lens = (l.len(), r.len());
+            ((l, r), lens)
+        });
+        assert_eq!(lens, (0, 7));
+
+        let () = extend_mut_chunks(&mut buf, |[a, b, c]: [&'static mut [u8]; 3]| {
+            [fill(a, 3), fill(b, 4), fill(c, 5)]
+        });
+        assert_eq!(buf, [3, 3, 3, 4, 4, 5, 5]);
+        let lens = extend_mut_chunks(&mut buf[..2], |chunks: [_; 4]| {
+            let lens = chunks.each_ref().map(|chunk| chunk.len());
+            (chunks, lens)
+        });
+        assert_eq!(lens, [1, 1, 0, 0]);
+
+        let () = extend_mut_split(&mut units, 1, |l, r| (l, r));
+        let () = extend_mut_chunks::<_, 2, _, _, _>(&mut units, |it| it);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_extend_mut_map_values() {
+        use std::collections::{BTreeMap, HashMap};
+
+        let mut hash = HashMap::from([(1, 10), (2, 20), (3, 30)]);
+        let () =
+            extend_mut_hash_map_values(&mut hash, [&3, &1], |[a, b]| [bump(a), bump(b)]).unwrap();
+        assert_eq!(hash, HashMap::from([(1, 11), (2, 20), (3, 31)]));
+        assert_eq!(
+            extend_mut_hash_map_values(&mut hash, [&1, &4], |it| it),
+            None
+        );
+
+        let mut btree: BTreeMap<String, i32> = BTreeMap::from([("a".into(), 1), ("b".into(), 2)]);
+        let sum = extend_mut_btree_map_values(&mut btree, ["b", "a"], |[b, a]| {
+            let sum = *a + *b;
+            ([bump(b), a], sum)
+        });
+        assert_eq!(sum, Some(3));
+        assert_eq!(btree["b"], 3);
+        assert_eq!(
+            extend_mut_btree_map_values(&mut btree, ["c"], |it| it),
+            None
+        );
+        let () =
+            extend_mut_btree_map_values::<_, _, str, 0, _, _, _>(&mut btree, [], |it| it).unwrap();
+
+        let mut btree: BTreeMap<i32, i32> =
+            (0..100).filter(|k| k % 2 == 0).map(|k| (k, k)).collect();
+        extend_mut_btree_map_values(&mut btree, [&90, &4, &50], |[x, y, z]| {
+            core::mem::swap(x, y);
+            [x, y, bump(z)]
+        });
+        assert_eq!((btree[&90], btree[&4], btree[&50]), (4, 90, 51));
+        assert_eq!(
+            extend_mut_btree_map_values(&mut btree, [&90, &51, &4], |it| it),
+            None
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    #[should_panic(expected = "duplicate keys")]
+    fn test_extend_mut_btree_map_duplicate_keys() {
+        let mut btree = std::collections::BTreeMap::from([(1, 1)]);
+        extend_mut_btree_map_values(&mut btree, [&1, &1], |it| it);
+    }
+
+    #[test]
+    fn test_extend_mut_option_result() {
+        let (mut x, mut y) = (1, 2);
+
+        let () = Some(&mut x).extend_mut(|x: Option<&'static mut i32>| x.map(bump));
+        let () = None::<&mut i32>.extend_mut(|x| x);
+        let "hi" = Ok::<_, ()>(&mut x).extend_mut(|x| (x.map(bump), "hi")) else {
+            panic!()
+        };
+        let e = Err::<&mut i32, _>("e").extend_mut(|x| {
+            let e = x.unwrap_err();
+            (Err(e), e)
+        });
+        assert_eq!(e, "e");
+        assert_eq!(x, 3);
+
+        let () =
+            (Some(&mut x), &mut y, None::<&mut u8>).extend_mut(|(x, y, z)| (x.map(bump), y, z));
+        let () = Some((&mut x, [&mut y])).extend_mut(|it| it);
+        assert_eq!(x, 4);
+    }
+
+    #[test]
+    fn test_extend_mut_nested_handles() {
+        let (mut x, mut y, mut z) = (0, 2, 3);
+
+        let () = [Some(&mut x), None].extend_mut(|[x, none]| [x.map(bump), none]);
+        let sum = ((&mut x, [&mut y]), Ok::<_, ()>(&mut z)).extend_mut(|((x, [y]), z)| {
+            let sum = *x + *y + *z.as_deref().unwrap();
+            (((x, [y]), z), sum)
+        });
+        assert_eq!(sum, 6);
+
+        #[cfg(feature = "alloc")]
+        {
+            let handles = alloc::vec![(&mut x, Some(&mut y)), (&mut z, None)];
+            let () = handles.extend_mut(|mut v| {
+                v.reverse();
+                v.reverse();
+                v
+            });
+        }
+    }
+
+    #[test]
+    fn test_extend_mut_guard() {
+        use core::cell::RefCell;
+
+        let cell = RefCell::new(0);
+        let () = cell.borrow_mut().extend_mut(bump);
+        let (guard, "hi") = extend_mut_guard(cell.borrow_mut(), |x| (bump(x), "hi")) else {
+            panic!()
+        };
+        assert!(cell.try_borrow().is_err());
+        drop(guard);
+        assert_eq!(*cell.borrow(), 2);
+
+        #[cfg(feature = "std")]
+        {
+            use std::sync::{Mutex, RwLock};
+
+            let (mutex, lock) = (Mutex::new(0), RwLock::new(0));
+            let guards = (mutex.lock().unwrap(), lock.write().unwrap());
+            let () = guards.extend_mut(|(x, y)| (bump(x), y));
+            let () = extend_mut_guard(lock.write().unwrap(), bump).1;
+            assert_eq!((*mutex.lock().unwrap(), *lock.read().unwrap()), (1, 1));
+        }
+    }
+
+    #[test]
+    fn test_into_extend_mut_return_macro() {
+        struct Channels<'a, T> {
+            rx: &'a mut T,
+            tx: &'a mut T,
+            received: usize,
+        }
+
+        crate::into_extend_mut_return! {
+            impl<'a, T> for Channels<'a, T> {
+                extended: { rx: &'a mut T, tx: &'a mut T },
+                rest: { received: usize },
+            }
+        }
+
+        struct Only<'a> {
+            x: &'a mut u8,
+        }
+
+        crate::into_extend_mut_return! {
+            impl<'a> for Only<'a> {
+                extended: { x: &'a mut u8 },
+                rest: {},
+            }
+        }
+
+        let (mut rx, mut tx) = ([1u8; 4], [0u8; 4]);
+        let received = (&mut rx, &mut tx).extend_mut(|(rx, tx)| {
+            tx.copy_from_slice(rx);
+            Channels {
+                rx,
+                tx,
+                received: 4,
+            }
+        });
+        assert_eq!((received, tx), (4, [1; 4]));
+
+        let () = extend_mut(&mut rx[0], |x: &'static mut u8| Only { x });
+
+        struct Parser<'a, B: ?Sized> {
+            buf: &'a mut B,
+            len: usize,
+        }
+
+        crate::into_extend_mut_return! {
+            impl<'a, B: ?Sized + AsMut<[u8]>,> for Parser<'a, B> {
+                extended: { buf: &'a mut B },
+                rest: { len: usize },
+            }
+        }
+
+        struct Tagged<'a, T, U> {
+            value: &'a mut T,
+            tag: U,
+        }
+
+        crate::into_extend_mut_return! {
+            impl<'a, T, U> for Tagged<'a, T, U>
+            where
+                T: Copy + core::fmt::Debug,
+                U: Into<u64>,
+            {
+                extended: { value: &'a mut T },
+                rest: { tag: U },
+            }
+        }
+
+        let len = extend_mut(&mut rx[..], |buf: &'static mut [u8]| Parser {
+            len: buf.len(),
+            buf,
+        });
+        let tag = extend_mut(&mut tx[0], |value| Tagged { value, tag: 7u8 });
+        assert_eq!((len, tag), (4, 7));
+    }
+
+    #[test]
+    fn test_extend_fields() {
+        struct Timer {
+            deadline: u64,
+        }
+
+        struct State {
+            rx: [u8; 2],
+            tx: [u8; 2],
+            timer: Timer,
+            pair: (u8, u8),
+        }
+
+        fn arm(deadline: &'static mut u64) -> &'static mut u64 {
+            *deadline = 10;
+            deadline
+        }
+
+        let mut state = State {
+            rx: [1; 2],
+            tx: [0; 2],
+            timer: Timer { deadline: 0 },
+            pair: (0, 0),
+        };
+
+        let () = crate::extend_fields!(state => rx, tx, timer.deadline; |rx, tx, deadline| {
+            tx.copy_from_slice(rx);
+            (rx, tx, arm(deadline))
+        });
+        assert_eq!((state.tx, state.timer.deadline), ([1; 2], 10));
+
+        let state = &mut state;
+        let first = crate::extend_fields!(*state => pair.0; move |first| {
+            let value = *first;
+            ((first,), value)
+        });
+        assert_eq!(first, 0);
+
+        fn counted<'s>(calls: &mut u32, state: &'s mut State) -> &'s mut State {
+            *calls += 1;
+            state
+        }
+
+        let mut calls = 0;
+        let () = crate::extend_fields!(*counted(&mut calls, state) => rx, pair.1; |rx, second| {
+            *second = rx[0];
+            (rx, second)
+        });
+        assert_eq!((calls, state.pair.1), (1, 1));
+
+        type Buf = &'static mut [u8; 2];
+        let () = crate::extend_fields!(*state => tx, timer.deadline; |tx: Buf, mut deadline,| {
+            tx.fill(2);
+            deadline = arm(deadline);
+            (tx, deadline)
+        });
+        assert_eq!(state.tx, [2; 2]);
+
+        let first = crate::extend_fields!(*state => pair.0; move |first: &'static mut u8| {
+            *first += 1;
+            ((first,), 7)
+        });
+        assert_eq!((first, state.pair.0), (7, 1));
+    }
+
+    #[test]
+    fn test_back_and_with() {
+        fn lend<E: for<'b> ExtendMut<'b>>(handles: E) {
+            handles.extend_mut(Back)
+        }
+
+        fn len<E: for<'b> ExtendMut<'b>>(handles: E, len: usize) -> usize {
+            handles.extend_mut(|it| With(it, len))
+        }
+
+        let (mut x, mut y) = (1, 2);
+        lend((&mut x, [&mut y]));
+        assert_eq!(len(Some(&mut x), 1), 1);
+
+        let () = extend_mut(&mut x, Back);
+        let (a, b) = extend_mut(&mut x, |x| With(x, (Back(1), 2)));
+        assert_eq!((a.0, b), (1, 2));
+    }
+
+    #[test]
+    fn test_try_extend_mut() {
+        fn check(
+            x: &'static mut i32,
+        ) -> Result<&'static mut i32, (&'static mut i32, &'static str)> {
+            if *x < 0 { Err((x, "negative")) } else { Ok(x) }
+        }
+
+        let (mut x, mut y) = (1, -1);
+        assert_eq!(try_extend_mut(&mut x, check), Ok(()));
+        assert_eq!(try_extend_mut(&mut y, check), Err("negative"));
+
+        let positive = |x: &i32| if *x < 0 { Err("negative") } else { Ok(*x) };
+
+        let res: Result<i32, &str> = (&mut x, &mut y).try_extend_mut(|(x, y)| {
+            let n = try_back!((x, y), positive(x));
+            Ok(((x, y), n + 1))
+        });
+        assert_eq!(res, Ok(2));
+
+        let res: Result<(), String> = (&mut y, &mut x).try_extend_mut(|(y, x)| {
+            try_back!((y, x), positive(y));
+            Ok(((y, x), ()))
+        });
+        assert_eq!(res, Err(String::from("negative")));
+    }
+
+    #[test]
+    fn test_extend_mut_unchecked() {
+        let (mut x, mut y) = (1, 2);
+        let mut empty: [u8; 0] = [];
+
+        let sum = unsafe { extend_mut_unchecked(&mut x, |x| (x, 1)) };
+        assert_eq!(sum, 1);
+        let sum = unsafe {
+            (&mut x, [&mut y], &mut empty[..]).extend_mut_unchecked(|(x, [y], e)| {
+                core::mem::swap(x, y);
+                let sum = *x + *y;
+                ((x, [y], e), sum)
+            })
+        };
+        assert_eq!((x, y, sum), (2, 1, 3));
+    }
+
+    #[test]
+    fn test_extend_mut_pin() {
+        use core::marker::PhantomPinned;
+        use core::pin::pin;
+
+        struct Node {
+            value: u32,
+            _pinned: PhantomPinned,
+        }
+
+        fn bump(node: Pin<&'static mut Node>) -> Pin<&'static mut Node> {
+            let mut node = node;
+            unsafe { node.as_mut().get_unchecked_mut().value += 1 };
+            node
+        }
+
+        let mut node = pin!(Node {
+            value: 0,
+            _pinned: PhantomPinned
+        });
+        let mut x = 0;
+
+        extend_mut_pin(node.as_mut(), bump);
+        let () = node.as_mut().extend_mut(bump);
+        let "hi" = node.as_mut().extend_mut(|n| (bump(n), "hi")) else {
+            panic!()
+        };
+        let () = (node.as_mut(), &mut x).extend_mut(|(n, x)| (bump(n), x));
+        let () = ((node.as_mut(),), (&mut x, ())).extend_mut(|it| it);
+
+        assert_eq!(node.value, 4);
+    }
+
+    #[test]
+    #[should_panic(expected = "ExtendMut: Zero-sized value cannot be lent here")]
+    fn test_extend_mut_pin_zero_sized() {
+        let mut unit = ();
+        extend_mut_pin(Pin::new(&mut unit), |u| u);
+    }
+
+    #[test]
+    #[should_panic(expected = "ExtendMut: Zero-sized value cannot be lent here")]
+    fn test_extend_mut_overaligned_zero_sized() {
+        #[repr(align(32))]
+        struct Overaligned;
+
+        extend_mut(&mut Overaligned, |o| o);
+    }
+
+    #[test]
+    fn test_error_display() {
+        let location = Location::caller();
+        let error = ExtendMutError::pointer_changed::<[u8]>(location);
+        assert_eq!(error.location(), location);
+        assert_eq!(error.type_name(), "[u8]");
+        assert_eq!(
+            error.to_string(),
+            format!("ExtendMut: Pointer changed (`[u8]` at {location})")
+        );
+    }
+
     #[test]
     #[cfg(feature = "async")]
     fn test_extend_mut_async_immediate() {
@@ -349,6 +1539,313 @@ mod test {
         assert_eq!(ret, 8);
     }
 
+    #[test]
+    #[cfg(feature = "async")]
+    #[allow(unused_unsafe)]
+    fn test_extend_mut_pin_async() {
+        use core::pin::pin;
+        use core::task::{Context, Poll, Waker};
+
+        use core::future::{Ready, ready};
+
+        let mut inner = pin!(ready(5));
+
+        async fn want_static(
+            mut fut: Pin<&'static mut Ready<i32>>,
+        ) -> (Pin<&'static mut Ready<i32>>, i32) {
+            let x = fut.as_mut().await;
+            (fut, x + 1)
+        }
+
+        let fut = unsafe { extend_mut_pin_async(inner.as_mut(), async |f| want_static(f).await) };
+        let mut fut = pin!(fut);
+        let ret = match fut.as_mut().poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(ret) => ret,
+            Poll::Pending => panic!(),
+        };
+
+        assert_eq!(ret, 6);
+    }
+
+    #[test]
+    #[cfg(all(feature = "async", feature = "assume-non-forget"))]
+    fn test_extend_mut_async_array() {
+        use core::pin::pin;
+        use core::task::{Context, Poll, Waker};
+
+        let (mut x, mut y) = (1, 2);
+
+        async fn swap(x: &'static mut i32, y: &'static mut i32) -> [&'static mut i32; 2] {
+            core::mem::swap(x, y);
+            [x, y]
+        }
+
+        let fut = [&mut x, &mut y].extend_mut_async(async |[x, y]| (swap(x, y).await, 3));
+        let ret = match pin!(fut).poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(ret) => ret,
+            Poll::Pending => panic!(),
+        };
+
+        assert_eq!(ret, 3);
+        assert_eq!((x, y), (2, 1));
+
+        // Handles are only lent once the future is pinned, so zero-sized values are
+        // supported.
+        let mut unit = ();
+        let fut = (&mut x, &mut unit).extend_mut_async(async |it| it);
+        let () = match pin!(fut).poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(ret) => ret,
+            Poll::Pending => panic!(),
+        };
+
+        // Nothing is lent before the first poll, so the future can be dropped.
+        drop((&mut x, [&mut y]).extend_mut_async(async |it| it));
+
+        let mut yielded = false;
+        let fut = (&mut x, Some(&mut y)).extend_mut_async(async |(x, y)| {
+            core::future::poll_fn(|cx| match core::mem::replace(&mut yielded, true) {
+                true => Poll::Ready(()),
+                false => {
+                    cx.waker().wake_by_ref();
+                    Poll::Pending
+                }
+            })
+            .await;
+            ((x, y), 4)
+        });
+        let mut fut = pin!(fut);
+        let mut cx = Context::from_waker(Waker::noop());
+        assert_eq!(fut.as_mut().poll(&mut cx), Poll::Pending);
+        assert_eq!(fut.as_mut().poll(&mut cx), Poll::Ready(4));
+        assert_eq!(fut.as_mut().poll(&mut cx), Poll::Pending);
+    }
+
+    #[test]
+    #[cfg(feature = "async")]
+    fn test_extend_mut_async_unchecked() {
+        use core::pin::pin;
+        use core::task::{Context, Poll, Waker};
+
+        let (mut x, mut y) = (1, 2);
+
+        async fn swap(
+            x: &'static mut i32,
+            y: &'static mut i32,
+        ) -> (&'static mut i32, &'static mut i32) {
+            core::mem::swap(x, y);
+            (x, y)
+        }
+
+        let fut = unsafe {
+            (&mut x, Some(&mut y)).extend_mut_async_unchecked(async |(x, y)| match y {
+                Some(y) => {
+                    let (x, y) = swap(x, y).await;
+                    ((x, Some(y)), 5)
+                }
+                None => ((x, None), 0),
+            })
+        };
+        let ret = match pin!(fut).poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(ret) => ret,
+            Poll::Pending => panic!(),
+        };
+
+        assert_eq!(ret, 5);
+        assert_eq!((x, y), (2, 1));
+    }
+
+    #[test]
+    #[cfg(all(feature = "async", feature = "std"))]
+    fn test_extend_mut_async_unchecked_zero_sized() {
+        use core::task::{Context, Waker};
+        use std::panic::{AssertUnwindSafe, catch_unwind};
+
+        #[repr(align(32))]
+        struct Overaligned;
+
+        let (mut x, mut over) = (0, Overaligned);
+        let fut = unsafe { (&mut x, &mut over).extend_mut_async_unchecked(async |it| it) };
+        let mut fut = Box::pin(fut);
+        let cx = &mut Context::from_waker(Waker::noop());
+        let panic = catch_unwind(AssertUnwindSafe(|| fut.as_mut().poll(cx))).unwrap_err();
+        let message = panic.downcast_ref::<String>().unwrap();
+        assert!(message.starts_with("ExtendMut: Zero-sized value cannot be lent here"));
+        // The closure was not taken, so nothing is lent and dropping does not abort.
+        drop(fut);
+    }
+
+    #[test]
+    #[cfg(feature = "async")]
+    fn test_extend_mut_async_unchecked_not_unpin() {
+        use core::{
+            future::{Ready, ready},
+            task::{Context, Waker},
+        };
+
+        // The inherent method is only picked when `T: Unpin`.
+        struct Probe<T>(PhantomData<T>);
+        trait NotUnpin {
+            fn is_unpin(&self) -> bool {
+                false
+            }
+        }
+        impl<T> NotUnpin for Probe<T> {}
+        impl<T: Unpin> Probe<T> {
+            fn is_unpin(&self) -> bool {
+                true
+            }
+        }
+        fn probe<T>(_: &T) -> Probe<T> {
+            Probe(PhantomData)
+        }
+
+        let mut x = 0;
+        let lend = |x: &'static mut i32| -> Ready<&'static mut i32> { ready(x) };
+        assert!(probe(&ready(())).is_unpin());
+
+        // `raw` lives inside the future, so it must stay `!Unpin` even if the closure's
+        // future is `Unpin`.
+        let fut = unsafe { (&mut x).extend_mut_async_unchecked(lend) };
+        assert!(!probe(&fut).is_unpin());
+        let _ = core::pin::pin!(fut).poll(&mut Context::from_waker(Waker::noop()));
+    }
+
+    #[test]
+    #[cfg(feature = "stable-async")]
+    fn test_extend_mut_future_send_sync() {
+        use core::{cell::Cell, future::Ready};
+
+        fn assert_send<T: Send>() {}
+        fn assert_sync<T: Sync>() {}
+
+        type Lend<T> = fn(&'static mut T) -> Ready<&'static mut T>;
+
+        assert_send::<ExtendMutFuture<'static, 'static, [u8], Lend<[u8]>, (), ()>>();
+        assert_sync::<ExtendMutFuture<'static, 'static, [u8], Lend<[u8]>, (), ()>>();
+        // `R` is never stored, so it does not matter.
+        assert_send::<ExtendMutFuture<'static, 'static, i32, Lend<i32>, *mut i32, ()>>();
+        // A `Send` future does not need `T: Sync` either.
+        assert_send::<ExtendMutFuture<'static, 'static, Cell<i32>, Lend<Cell<i32>>, (), ()>>();
+    }
+
+    #[test]
+    #[cfg(feature = "async")]
+    fn test_extend_mut_async_send_sync() {
+        use core::cell::Cell;
+
+        fn assert_send<T: Send>(_: &T) {}
+        fn assert_sync<T: Sync>(_: &T) {}
+
+        let (mut x, mut y, mut cell) = (1, [2u8; 2], Cell::new(3));
+
+        let fut = unsafe { (&mut x, &mut y[..]).extend_mut_async_unchecked(async |it| it) };
+        assert_send(&fut);
+        assert_sync(&fut);
+        drop(fut);
+
+        // A `Send` future does not need `T: Sync`.
+        let fut = unsafe { (&mut cell).extend_mut_async_unchecked(async |it| it) };
+        assert_send(&fut);
+        drop(fut);
+
+        #[cfg(feature = "assume-non-forget")]
+        {
+            let fut = [&mut x].extend_mut_async(async |it| it);
+            assert_send(&fut);
+            assert_sync(&fut);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "stable-async")]
+    #[allow(unused_unsafe)]
+    fn test_extend_mut_future() {
+        use core::pin::pin;
+        use core::task::{Context, Poll, Waker};
+
+        let mut x = [1u8, 2];
+        let mut cx = Context::from_waker(Waker::noop());
+
+        async fn sum(x: &'static mut [u8]) -> (&'static mut [u8], u8) {
+            let sum = x.iter().sum();
+            (x, sum)
+        }
+
+        let fut = unsafe { extend_mut_future(&mut x[..], sum) };
+        assert_eq!(pin!(fut).poll(&mut cx), Poll::Ready(3));
+
+        {
+            let fut = unsafe {
+                extend_mut_future(&mut x, |x| async move {
+                    x.reverse();
+                    x
+                })
+            };
+            let mut fut = pin!(fut);
+            assert_eq!(fut.as_mut().poll(&mut cx), Poll::Ready(()));
+            assert_eq!(fut.as_mut().poll(&mut cx), Poll::Pending);
+        }
+        assert_eq!(x, [2, 1]);
+
+        // Zero-sized values are lent through a token inside the pinned future.
+        let fut = unsafe { extend_mut_future(&mut x[..0], sum) };
+        assert_eq!(pin!(fut).poll(&mut cx), Poll::Ready(0));
+        let mut unit = ();
+        let fut = unsafe { extend_mut_future(&mut unit, |u| async { u }) };
+        assert_eq!(pin!(fut).poll(&mut cx), Poll::Ready(()));
+        // Nothing is lent before the first poll, so the future can be dropped.
+        drop(unsafe { extend_mut_future(&mut unit, |u| async { u }) });
+    }
+
+    #[test]
+    #[cfg(all(feature = "std", feature = "stable-async"))]
+    #[allow(unused_unsafe)]
+    fn test_extend_mut_future_block_on_drop() {
+        use core::pin::pin;
+        use core::task::{Context, Poll, Waker};
+        use std::sync::{
+            Arc,
+            atomic::{AtomicBool, Ordering},
+        };
+
+        let mut x = 1;
+
+        // Dropped before the first poll.
+        drop(unsafe { extend_mut_future(&mut x, |x| async { x }) }.block_on_drop());
+
+        // Dropped while pending, and woken from another thread.
+        {
+            let fut = unsafe {
+                extend_mut_future(&mut x, |x| async move {
+                    let done = Arc::new(AtomicBool::new(false));
+                    core::future::poll_fn(|cx| {
+                        if done.load(Ordering::Acquire) {
+                            return Poll::Ready(());
+                        }
+                        let (done, waker) = (done.clone(), cx.waker().clone());
+                        std::thread::spawn(move || {
+                            done.store(true, Ordering::Release);
+                            waker.wake();
+                        });
+                        Poll::Pending
+                    })
+                    .await;
+                    *x += 1;
+                    x
+                })
+            };
+            let mut fut = pin!(fut.block_on_drop());
+            let cx = &mut Context::from_waker(Waker::noop());
+            assert_eq!(fut.as_mut().poll(cx), Poll::Pending);
+        }
+        assert_eq!(x, 2);
+
+        // Polled to completion, so the drop has nothing to wait for.
+        let fut = unsafe { extend_mut_future(&mut x, |x| async { (x, 3) }) };
+        let cx = &mut Context::from_waker(Waker::noop());
+        assert_eq!(pin!(fut.block_on_drop()).poll(cx), Poll::Ready(3));
+    }
+
     #[test]
     #[cfg(feature = "async")]
     fn test_extend_mut_async_yielding() {
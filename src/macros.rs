@@ -0,0 +1,224 @@
+/*!
+
+Declarative macros for plugging user types into the crate.
+
+*/
+
+/// Implements [`IntoExtendMutReturn`](crate::IntoExtendMutReturn) for a struct, so a
+/// closure can return it instead of a tuple.
+///
+/// Fields listed under `extended` are given back, and fields listed under `rest`
+/// become `R`. A single field is used as is, several fields become a tuple, and an
+/// empty `rest` becomes `()`. Every field of the struct has to be listed, and the
+/// struct cannot implement `Drop`, so the generated impl only moves fields out and
+/// cannot unwind.
+///
+/// Generic parameters and `where` predicates take bounds joined with `+`, such as
+/// `B: ?Sized + core::fmt::Debug + Into<u8>`. A `?` bound has to come first, and when
+/// the last bound of the generic parameters has generic arguments, the list has to end
+/// with a comma, as in `impl<B: AsMut<[u8]>,>`, because `>>` cannot be split. Lifetime
+/// bounds, `Fn(..)` bounds and higher-ranked bounds are not supported. Implement
+/// [`IntoExtendMutReturn`](crate::IntoExtendMutReturn) by hand for those.
+///
+/// ```
+/// # #[cfg(feature = "extern-abort-hook")] extend_mut::abort_hook!(|_| {});
+/// use extend_mut::{extend_mut, into_extend_mut_return};
+///
+/// struct Parsed<'a> {
+///     buf: &'a mut [u8],
+///     len: usize,
+///     checksum: u8,
+/// }
+///
+/// into_extend_mut_return! {
+///     impl<'a> for Parsed<'a> {
+///         extended: { buf: &'a mut [u8] },
+///         rest: { len: usize, checksum: u8 },
+///     }
+/// }
+///
+/// fn parse(buf: &'static mut [u8]) -> Parsed<'static> {
+///     let checksum = buf.iter().fold(0, |acc: u8, b| acc.wrapping_add(*b));
+///     Parsed { len: buf.len(), buf, checksum }
+/// }
+///
+/// let mut buf = [1, 2, 3];
+/// let (len, checksum) = extend_mut(&mut buf[..], parse);
+/// assert_eq!((len, checksum), (3, 6));
+/// ```
+#[macro_export]
+macro_rules! into_extend_mut_return {
+    (
+        impl $(<
+            $($lt:lifetime),* $(,)?
+            $($param:ident $(:
+                $(?$relaxed:ident)?
+                $($(+)? $($seg:ident)::+ $(<$($arg:ty),+ $(,)?>)?)*
+            )?),* $(,)?
+        >)? for $ty:ty $(where $(
+            $where_ty:ty:
+                $(?$where_relaxed:ident)?
+                $($(+)? $($where_seg:ident)::+ $(<$($where_arg:ty),+ $(,)?>)?)*
+        ),* $(,)?)? {
+            extended: { $($ext:ident: $ext_ty:ty),* $(,)? },
+            rest: { $($rest:ident: $rest_ty:ty),* $(,)? } $(,)?
+        }
+    ) => {
+        unsafe impl $(<
+            $($lt,)*
+            $($param $(: $(?$relaxed +)? $($($seg)::+ $(<$($arg),+>)? +)*)?,)*
+        >)? $crate::IntoExtendMutReturn<
+            $crate::into_extend_mut_return!(@ty $($ext_ty),*),
+            $crate::into_extend_mut_return!(@ty $($rest_ty),*),
+        > for $ty
+        $(where $(
+            $where_ty: $(?$where_relaxed +)? $($($where_seg)::+ $(<$($where_arg),+>)? +)*,
+        )*)?
+        {
+            #[inline(always)]
+            fn into_extend_mut_return(
+                self,
+            ) -> (
+                $crate::into_extend_mut_return!(@ty $($ext_ty),*),
+                $crate::into_extend_mut_return!(@ty $($rest_ty),*),
+            ) {
+                let Self { $($ext,)* $($rest,)* } = self;
+                (
+                    $crate::into_extend_mut_return!(@expr $($ext),*),
+                    $crate::into_extend_mut_return!(@expr $($rest),*),
+                )
+            }
+        }
+    };
+    (@ty) => { () };
+    (@ty $ty:ty) => { $ty };
+    (@ty $($ty:ty),+) => { ($($ty,)+) };
+    (@expr) => { () };
+    (@expr $field:ident) => { $field };
+    (@expr $($field:ident),+) => { ($($field,)+) };
+}
+
+/// Extends several fields of one struct at once.
+///
+/// `extend_fields!(base => a, b.c; |a, c| ...)` lends `&mut base.a` and `&mut base.b.c`
+/// through the tuple impl of [`ExtendMut`](crate::ExtendMut). The closure gets one
+/// argument per field, and returns the references as a tuple, optionally paired with
+/// a result: `(a, c)` or `((a, c), r)`. A single field is still returned as a
+/// one-element tuple: `(a,)` or `((a,), r)`. The borrow checker makes sure the fields
+/// are disjoint.
+///
+/// Arguments can be annotated, for example to pin `'static`: `|a: &'static mut u8, c|`.
+/// An annotated argument is an identifier, `_` or a parenthesized pattern, optionally
+/// with `mut`. Arguments without annotations can be any pattern.
+///
+/// `base` is evaluated once and borrowed mutably. To lend the fields behind a
+/// reference `r`, pass `*r`.
+///
+/// ```
+/// # #[cfg(feature = "extern-abort-hook")] extend_mut::abort_hook!(|_| {});
+/// use extend_mut::extend_fields;
+///
+/// struct Io { rx: [u8; 4], tx: [u8; 4] }
+/// struct State { io: Io, ticks: u32 }
+///
+/// type Buf = &'static mut [u8; 4];
+///
+/// fn start(rx: Buf, tx: Buf) -> (Buf, Buf) {
+///     tx.copy_from_slice(rx);
+///     (rx, tx)
+/// }
+///
+/// let mut state = State { io: Io { rx: [1; 4], tx: [0; 4] }, ticks: 0 };
+/// let started = extend_fields!(state => io.rx, io.tx, ticks; |rx: Buf, tx, ticks| {
+///     let (rx, tx) = start(rx, tx);
+///     *ticks += 1;
+///     ((rx, tx, ticks), true)
+/// });
+/// assert!(started);
+/// assert_eq!((state.io.tx, state.ticks), ([1; 4], 1));
+/// ```
+#[macro_export]
+macro_rules! extend_fields {
+    // Typed closure arguments are munched one at a time into a tuple pattern and a tuple
+    // type, with `_` for arguments without a type.
+    (@args $head:tt [$($pat:tt)*] [$($ty:tt)*] | $($rest:tt)*) => {
+        $crate::extend_fields!(@body $head [$($pat)*] [$($ty)*] $($rest)*)
+    };
+    (@args $head:tt [$($pat:tt)*] [$($ty:tt)*] mut $arg:tt: $arg_ty:ty, $($rest:tt)*) => {
+        $crate::extend_fields!(@args $head [$($pat)* mut $arg,] [$($ty)* $arg_ty,] $($rest)*)
+    };
+    (@args $head:tt [$($pat:tt)*] [$($ty:tt)*] mut $arg:tt: $arg_ty:ty| $($rest:tt)*) => {
+        $crate::extend_fields!(@body $head [$($pat)* mut $arg,] [$($ty)* $arg_ty,] $($rest)*)
+    };
+    (@args $head:tt [$($pat:tt)*] [$($ty:tt)*] mut $arg:tt, $($rest:tt)*) => {
+        $crate::extend_fields!(@args $head [$($pat)* mut $arg,] [$($ty)* _,] $($rest)*)
+    };
+    (@args $head:tt [$($pat:tt)*] [$($ty:tt)*] mut $arg:tt| $($rest:tt)*) => {
+        $crate::extend_fields!(@body $head [$($pat)* mut $arg,] [$($ty)* _,] $($rest)*)
+    };
+    (@args $head:tt [$($pat:tt)*] [$($ty:tt)*] $arg:tt: $arg_ty:ty, $($rest:tt)*) => {
+        $crate::extend_fields!(@args $head [$($pat)* $arg,] [$($ty)* $arg_ty,] $($rest)*)
+    };
+    (@args $head:tt [$($pat:tt)*] [$($ty:tt)*] $arg:tt: $arg_ty:ty| $($rest:tt)*) => {
+        $crate::extend_fields!(@body $head [$($pat)* $arg,] [$($ty)* $arg_ty,] $($rest)*)
+    };
+    (@args $head:tt [$($pat:tt)*] [$($ty:tt)*] $arg:tt, $($rest:tt)*) => {
+        $crate::extend_fields!(@args $head [$($pat)* $arg,] [$($ty)* _,] $($rest)*)
+    };
+    (@args $head:tt [$($pat:tt)*] [$($ty:tt)*] $arg:tt| $($rest:tt)*) => {
+        $crate::extend_fields!(@body $head [$($pat)* $arg,] [$($ty)* _,] $($rest)*)
+    };
+    (@body [$base:expr; $($($field:tt).+),+; $($move:ident)?] [$($pat:tt)*] [$($ty:tt)*] $body:expr) => {
+        {
+            let base = &mut $base;
+            $crate::ExtendMut::extend_mut(
+                ($(&mut base.$($field).+,)+),
+                $($move)? |($($pat)*): ($($ty)*)| $body,
+            )
+        }
+    };
+    ($base:expr => $($($field:tt).+),+; |$($arg:pat_param),+ $(,)?| $body:expr) => {
+        {
+            let base = &mut $base;
+            $crate::ExtendMut::extend_mut(($(&mut base.$($field).+,)+), |($($arg,)+)| $body)
+        }
+    };
+    ($base:expr => $($($field:tt).+),+; move |$($arg:pat_param),+ $(,)?| $body:expr) => {
+        {
+            let base = &mut $base;
+            $crate::ExtendMut::extend_mut(($(&mut base.$($field).+,)+), move |($($arg,)+)| $body)
+        }
+    };
+    ($base:expr => $($($field:tt).+),+; move |$($args:tt)*) => {
+        $crate::extend_fields!(@args [$base; $($($field).+),+; move] [] [] $($args)*)
+    };
+    ($base:expr => $($($field:tt).+),+; |$($args:tt)*) => {
+        $crate::extend_fields!(@args [$base; $($($field).+),+;] [] [] $($args)*)
+    };
+}
+
+/// Like `?` inside a [`try_extend_mut`](crate::try_extend_mut) closure: evaluates to
+/// the `Ok` value, or returns `Err((refs, e.into()))` so the references go back too.
+///
+/// ```
+/// # #[cfg(feature = "extern-abort-hook")] extend_mut::abort_hook!(|_| {});
+/// use extend_mut::{ExtendMut, try_back};
+///
+/// let (mut a, mut b) = (1, 2);
+/// let res: Result<(), String> = (&mut a, &mut b).try_extend_mut(|(a, b)| {
+///     let n: i32 = try_back!((a, b), "x".parse::<i32>().map_err(|e| e.to_string()));
+///     Ok(((a, b), ()))
+/// });
+/// assert!(res.is_err());
+/// ```
+#[macro_export]
+macro_rules! try_back {
+    ($refs:expr, $res:expr $(,)?) => {
+        match $res {
+            ::core::result::Result::Ok(value) => value,
+            ::core::result::Result::Err(error) => {
+                return ::core::result::Result::Err(($refs, ::core::convert::From::from(error)));
+            }
+        }
+    };
+}
@@ -0,0 +1,131 @@
+/*!
+
+Lending values of several distinct keys of a map at once.
+
+The values are looked up first and then lent through the array impl of
+[`ExtendMut`], so each of them is checked on return like in `extend_mut`.
+
+*/
+
+use core::{borrow::Borrow, cmp::Ordering, ops::Bound, panic::Location};
+
+use alloc::collections::BTreeMap;
+#[cfg(feature = "std")]
+use {
+    core::hash::{BuildHasher, Hash},
+    std::collections::HashMap,
+};
+
+use crate::{ExtendMut, IntoExtendMutReturn};
+
+/// Extends the values of `keys` in `map`, like [`HashMap::get_disjoint_mut`]. `f` must
+/// return the same values, in the same order, otherwise the process is aborted.
+///
+/// Returns `None` without calling `f` if any of the keys is missing.
+///
+/// # Panics
+///
+/// Panics if any keys are equal.
+///
+/// ```
+/// # #[cfg(feature = "extern-abort-hook")] extend_mut::abort_hook!(|_| {});
+/// use std::collections::HashMap;
+/// use extend_mut::extend_mut_hash_map_values;
+///
+/// let mut sessions = HashMap::from([("a", 1), ("b", 2)]);
+/// let sum = extend_mut_hash_map_values(&mut sessions, ["a", "b"], |[a, b]| {
+///     let (a, b): (&'static mut i32, &'static mut i32) = (a, b);
+///     let sum = *a + *b;
+///     ([a, b], sum)
+/// });
+/// assert_eq!(sum, Some(3));
+/// assert_eq!(extend_mut_hash_map_values(&mut sessions, ["c"], |it| it), None);
+/// ```
+#[cfg(feature = "std")]
+#[inline(always)]
+#[track_caller]
+pub fn extend_mut_hash_map_values<'a, 'b, K, V, S, Q, const N: usize, F, R, ExtR>(
+    map: &'a mut HashMap<K, V, S>,
+    keys: [&Q; N],
+    f: F,
+) -> Option<R>
+where
+    K: Borrow<Q> + Hash + Eq,
+    Q: Hash + Eq + ?Sized,
+    S: BuildHasher,
+    V: 'b,
+    F: FnOnce([&'b mut V; N]) -> ExtR,
+    ExtR: IntoExtendMutReturn<[&'b mut V; N], R>,
+{
+    let location = Location::caller();
+    let values = map.get_disjoint_mut(keys);
+    if values.iter().any(Option::is_none) {
+        return None;
+    }
+    Some(values.map(Option::unwrap).extend_mut_at(f, location))
+}
+
+/// Same as [`extend_mut_hash_map_values`], but for a [`BTreeMap`].
+///
+/// `keys` are sorted, then the values are collected in a single pass over the entries
+/// between the smallest and the largest of them.
+///
+/// # Panics
+///
+/// Panics if any keys are equal.
+///
+/// ```
+/// # #[cfg(feature = "extern-abort-hook")] extend_mut::abort_hook!(|_| {});
+/// use std::collections::BTreeMap;
+/// use extend_mut::extend_mut_btree_map_values;
+///
+/// let mut sessions = BTreeMap::from([(1, 'a'), (2, 'b'), (3, 'c')]);
+/// extend_mut_btree_map_values(&mut sessions, [&3, &1], |[c, a]: [&'static mut char; 2]| {
+///     core::mem::swap(c, a);
+///     [c, a]
+/// });
+/// assert_eq!(sessions, BTreeMap::from([(1, 'c'), (2, 'b'), (3, 'a')]));
+/// ```
+#[inline(always)]
+#[track_caller]
+pub fn extend_mut_btree_map_values<'a, 'b, K, V, Q, const N: usize, F, R, ExtR>(
+    map: &'a mut BTreeMap<K, V>,
+    keys: [&Q; N],
+    f: F,
+) -> Option<R>
+where
+    K: Borrow<Q> + Ord,
+    Q: Ord + ?Sized,
+    V: 'b,
+    F: FnOnce([&'b mut V; N]) -> ExtR,
+    ExtR: IntoExtendMutReturn<[&'b mut V; N], R>,
+{
+    let location = Location::caller();
+    let mut order: [usize; N] = core::array::from_fn(|i| i);
+    order.sort_unstable_by(|&a, &b| keys[a].cmp(keys[b]));
+    if order.windows(2).any(|w| keys[w[0]] == keys[w[1]]) {
+        panic!("duplicate keys passed to `extend_mut_btree_map_values`");
+    }
+
+    let mut values = [const { None }; N];
+    if let (Some(&first), Some(&last)) = (order.first(), order.last()) {
+        let range = (Bound::Included(keys[first]), Bound::Included(keys[last]));
+        let mut wanted = order.iter().peekable();
+        for (key, value) in map.range_mut::<Q, _>(range) {
+            let Some(&&i) = wanted.peek() else { break };
+            match key.borrow().cmp(keys[i]) {
+                Ordering::Less => {}
+                Ordering::Equal => {
+                    values[i] = Some(value);
+                    wanted.next();
+                }
+                // Walked past `keys[i]`, so it is missing.
+                Ordering::Greater => return None,
+            }
+        }
+    }
+    if values.iter().any(Option::is_none) {
+        return None;
+    }
+    Some(values.map(Option::unwrap).extend_mut_at(f, location))
+}
@@ -0,0 +1,44 @@
+/*!
+
+Parking executor behind [`BlockOnDrop`](crate::BlockOnDrop).
+
+A future dropped before it is ready still holds the lent reference, so instead
+of aborting it is driven to completion on the current thread, which is parked
+whenever the future is pending.
+
+*/
+
+use core::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll, Waker},
+};
+use std::{
+    sync::Arc,
+    task::Wake,
+    thread::{self, Thread},
+};
+
+struct Unparker(Thread);
+
+impl Wake for Unparker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Polls `future` to completion, parking the current thread while it is pending.
+pub(crate) fn block_on<F: Future + ?Sized>(mut future: Pin<&mut F>) -> F::Output {
+    let waker = Waker::from(Arc::new(Unparker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(ret) => return ret,
+            // Spurious wake-ups only cost an extra poll.
+            Poll::Pending => thread::park(),
+        }
+    }
+}
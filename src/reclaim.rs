@@ -0,0 +1,182 @@
+/*!
+
+Extension point for exclusive handles other than `&mut T`.
+
+A [`Reclaim`] handle is taken apart into a raw identity, rebuilt with a longer
+lifetime for the closure, and compared against the raw identity once it comes
+back. Every implementor gets [`ExtendMut`](crate::ExtendMut) and
+[`IntoExtendMutReturn`](crate::IntoExtendMutReturn) for free, so it also works
+inside tuples, arrays, `Option`s and so on.
+
+*/
+
+use core::{any::type_name, panic::Location, ptr};
+
+use crate::identity::{Identity, ZstToken};
+
+/// Exclusive handle that can be lent with the lifetime `'b` and recognized when it
+/// comes back.
+///
+/// ```
+/// # #[cfg(feature = "extern-abort-hook")] extend_mut::abort_hook!(|_| {});
+/// use extend_mut::{ExtendMut, Reclaim};
+///
+/// /// Exclusive access to one slot of an arena.
+/// struct Slot<'a>(&'a mut [u8; 16]);
+///
+/// unsafe impl<'b> Reclaim<'b> for Slot<'_> {
+///     type Extended = Slot<'b>;
+///     type Raw = *mut [u8; 16];
+///
+///     fn into_raw(self) -> Self::Raw {
+///         self.0
+///     }
+///     unsafe fn extend(raw: &mut Self::Raw) -> Slot<'b> {
+///         Slot(unsafe { &mut **raw })
+///     }
+///     fn is_reclaimed(raw: &Self::Raw, extended: Slot<'b>) -> bool {
+///         core::ptr::eq(*raw, extended.0)
+///     }
+/// }
+///
+/// fn fill(slot: Slot<'static>) -> Slot<'static> {
+///     slot.0.fill(1);
+///     slot
+/// }
+///
+/// let mut arena = [[0u8; 16]; 2];
+/// let [a, b] = &mut arena;
+/// let () = (Slot(a), Slot(b)).extend_mut(|(a, b)| (fill(a), b));
+/// assert_eq!(arena, [[1; 16], [0; 16]]);
+/// ```
+///
+/// # Safety
+///
+/// - The handle returned by `extend` must give access to nothing but what `self` gave
+///   access to.
+/// - `is_reclaimed` must return `true` only if `extended` is the handle returned by
+///   `extend` for the same `raw`, so that no copy of it can outlive the call.
+/// - `is_reclaimed` must not unwind.
+///
+/// Callers never move `raw` between `extend` and `is_reclaimed`, and call `extend` at
+/// most once for every `raw`.
+pub unsafe trait Reclaim<'b>: Sized {
+    /// The same handle with the lifetime `'b`.
+    type Extended;
+    /// Identity of the handle while it is lent out.
+    type Raw;
+
+    /// Takes the handle apart into its raw identity.
+    #[track_caller]
+    fn into_raw(self) -> Self::Raw;
+
+    /// Rebuilds the handle from its raw identity with the lifetime `'b`.
+    ///
+    /// # Safety
+    ///
+    /// `raw` must come from [`Reclaim::into_raw`], must not have been extended before,
+    /// and must not move until it is passed to [`Reclaim::is_reclaimed`].
+    unsafe fn extend(raw: &mut Self::Raw) -> Self::Extended;
+
+    /// Whether `extended` is the handle that was rebuilt from `raw`.
+    fn is_reclaimed(raw: &Self::Raw, extended: Self::Extended) -> bool;
+
+    /// Same as [`Reclaim::into_raw`], but reports `location` if it panics.
+    #[doc(hidden)]
+    fn into_raw_at(self, location: &'static Location<'static>, _: Internal) -> Self::Raw {
+        let _ = location;
+        self.into_raw()
+    }
+
+    /// Same as [`Reclaim::is_reclaimed`], but on failure names the handle that did not
+    /// come back, so composite handles can point at the element that changed.
+    #[doc(hidden)]
+    fn check_reclaimed(
+        raw: &Self::Raw,
+        extended: Self::Extended,
+        _: Internal,
+    ) -> Result<(), &'static str> {
+        match Self::is_reclaimed(raw, extended) {
+            true => Ok(()),
+            false => Err(Self::type_name(Internal)),
+        }
+    }
+
+    /// Name reported in [`ExtendMutError`](crate::ExtendMutError)s about this handle.
+    #[doc(hidden)]
+    fn type_name(_: Internal) -> &'static str {
+        type_name::<Self>()
+    }
+}
+
+/// Argument of the hidden [`Reclaim`] methods, which only the handles of this crate
+/// override.
+///
+/// It cannot be named outside the crate, so other crates can neither override nor call
+/// those methods, and the `# Safety` section of [`Reclaim`] covers everything they can
+/// implement.
+///
+/// ```compile_fail,E0050
+/// use extend_mut::Reclaim;
+///
+/// struct Unchecked<'a>(&'a mut u8);
+///
+/// unsafe impl<'b> Reclaim<'b> for Unchecked<'_> {
+///     type Extended = Unchecked<'b>;
+///     type Raw = *mut u8;
+///
+///     fn into_raw(self) -> *mut u8 {
+///         self.0
+///     }
+///     unsafe fn extend(raw: &mut *mut u8) -> Unchecked<'b> {
+///         Unchecked(unsafe { &mut **raw })
+///     }
+///     fn is_reclaimed(raw: &*mut u8, extended: Unchecked<'b>) -> bool {
+///         core::ptr::eq(*raw, extended.0)
+///     }
+///     fn check_reclaimed(_: &*mut u8, _: Unchecked<'b>) -> Result<(), &'static str> {
+///         Ok(())
+///     }
+/// }
+/// ```
+pub struct Internal;
+
+/// Raw identity of a lent `&mut T` or `Pin<&mut T>`, see [`Reclaim`].
+pub struct RawMut<T: ?Sized> {
+    identity: Identity<T>,
+    token: ZstToken,
+    location: &'static Location<'static>,
+}
+
+impl<T: ?Sized> RawMut<T> {
+    #[inline(always)]
+    pub(crate) fn new(mut_ref: &mut T, location: &'static Location<'static>) -> Self {
+        Self {
+            identity: Identity::new(ptr::from_mut(mut_ref)),
+            token: ZstToken::new(),
+            location,
+        }
+    }
+
+    /// Returns the pointer to lend out, see [`ZstToken::lend`].
+    ///
+    /// # Safety
+    /// Same as [`Reclaim::extend`].
+    #[inline(always)]
+    pub(crate) unsafe fn lend(&mut self) -> *mut T {
+        let ptr = unsafe { self.token.lend(self.identity.ptr(), self.location) };
+        self.identity = Identity::new(ptr);
+        ptr
+    }
+
+    #[inline(always)]
+    pub(crate) fn matches(&self, other: &T) -> bool {
+        self.identity.matches(other)
+    }
+
+    #[cfg(feature = "stable-async")]
+    #[inline(always)]
+    pub(crate) fn location(&self) -> &'static Location<'static> {
+        self.location
+    }
+}
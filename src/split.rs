@@ -0,0 +1,87 @@
+/*!
+
+Lending disjoint parts of one slice.
+
+A closure given the whole slice cannot return a sub-slice of it, because identity
+checks compare lengths too. These functions split the slice first and lend the
+pieces through the tuple and array impls of [`ExtendMut`], so every piece is
+checked on its own and the original partition has to come back unchanged.
+
+*/
+
+use core::panic::Location;
+
+use crate::{ExtendMut, IntoExtendMutReturn};
+
+/// Splits `slice` at `mid` and extends both halves, like [`slice::split_at_mut`].
+/// `f` must return the same two halves, in the same order, otherwise the process is
+/// aborted.
+///
+/// You can return either `(left, right)` or `((left, right), R)` from `f`.
+///
+/// # Panics
+///
+/// Panics if `mid > slice.len()`.
+///
+/// ```
+/// # #[cfg(feature = "extern-abort-hook")] extend_mut::abort_hook!(|_| {});
+/// use extend_mut::extend_mut_split;
+///
+/// fn consume(chunk: &'static mut [u8], byte: u8) -> &'static mut [u8] {
+///     chunk.fill(byte);
+///     chunk
+/// }
+///
+/// let mut buf = [0u8; 4];
+/// extend_mut_split(&mut buf, 1, |left, right| (consume(left, 1), consume(right, 2)));
+/// assert_eq!(buf, [1, 2, 2, 2]);
+/// ```
+#[inline(always)]
+#[track_caller]
+pub fn extend_mut_split<'a, 'b, T: 'b, F, R, ExtR>(slice: &'a mut [T], mid: usize, f: F) -> R
+where
+    F: FnOnce(&'b mut [T], &'b mut [T]) -> ExtR,
+    ExtR: IntoExtendMutReturn<(&'b mut [T], &'b mut [T]), R>,
+{
+    let location = Location::caller();
+    slice
+        .split_at_mut(mid)
+        .extend_mut_at(|(left, right)| f(left, right), location)
+}
+
+/// Splits `slice` into `N` consecutive chunks and extends all of them. `f` must return
+/// the same chunks, in the same order, otherwise the process is aborted.
+///
+/// Chunk lengths differ by at most one, the first `slice.len() % N` chunks being the
+/// longer ones. `N` must not be zero.
+///
+/// ```
+/// # #[cfg(feature = "extern-abort-hook")] extend_mut::abort_hook!(|_| {});
+/// use extend_mut::extend_mut_chunks;
+///
+/// let mut buf = [0u8; 5];
+/// let lens = extend_mut_chunks(&mut buf, |chunks: [&'static mut [u8]; 2]| {
+///     let lens = chunks.each_ref().map(|chunk| chunk.len());
+///     (chunks, lens)
+/// });
+/// assert_eq!(lens, [3, 2]);
+/// ```
+#[inline(always)]
+#[track_caller]
+pub fn extend_mut_chunks<'a, 'b, T: 'b, const N: usize, F, R, ExtR>(slice: &'a mut [T], f: F) -> R
+where
+    F: FnOnce([&'b mut [T]; N]) -> ExtR,
+    ExtR: IntoExtendMutReturn<[&'b mut [T]; N], R>,
+{
+    const { assert!(N != 0, "cannot split a slice into zero chunks") };
+
+    let location = Location::caller();
+    let (len, longer) = (slice.len() / N, slice.len() % N);
+    let mut rest = slice;
+    let chunks = core::array::from_fn(|i| {
+        let (chunk, tail) = core::mem::take(&mut rest).split_at_mut(len + usize::from(i < longer));
+        rest = tail;
+        chunk
+    });
+    chunks.extend_mut_at(f, location)
+}
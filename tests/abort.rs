@@ -0,0 +1,375 @@
+//! Abort paths can only be observed from another process, so every test re-runs this
+//! binary with `EXTEND_MUT_ABORT_CASE` set and inspects how the child died.
+
+use std::{
+    any::Any,
+    process::{Command, Output},
+};
+
+#[cfg(feature = "std")]
+use extend_mut::ExtendMutError;
+use extend_mut::{ExtendMut, Reclaim, extend_mut, extend_mut_split};
+
+const CASE_VAR: &str = "EXTEND_MUT_ABORT_CASE";
+
+/// Returns the output of the aborted child, or `None` when running as the child.
+fn run_case(case: &str) -> Option<Output> {
+    if let Ok(current) = std::env::var(CASE_VAR) {
+        if current == case {
+            run(case);
+            unreachable!("case `{case}` did not abort");
+        }
+        return None;
+    }
+
+    let output = Command::new(std::env::current_exe().unwrap())
+        .args([case, "--exact", "--nocapture", "--test-threads=1"])
+        .env(CASE_VAR, case)
+        .output()
+        .unwrap();
+    assert!(!output.status.success(), "case `{case}` did not abort");
+    Some(output)
+}
+
+/// Handle whose identity check always fails.
+struct Rejected;
+
+unsafe impl<'b> Reclaim<'b> for Rejected {
+    type Extended = Rejected;
+    type Raw = ();
+
+    fn into_raw(self) {}
+    unsafe fn extend(_: &mut ()) -> Rejected {
+        Rejected
+    }
+    fn is_reclaimed(_: &(), _: Rejected) -> bool {
+        false
+    }
+}
+
+/// Value whose first field can be given back as a trait object in place of it.
+trait Part: Any {
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+#[repr(C)]
+struct Whole<I> {
+    inner: I,
+    cap: Cap,
+}
+
+struct Cap;
+
+impl Part for u32 {
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+impl Part for () {
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+impl<I: 'static> Part for Whole<I> {
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// Gives back the first field of `whole` in place of it and keeps `cap`.
+fn keep_cap<I: Part>(whole: &'static mut dyn Part) -> &'static mut dyn Part {
+    let Whole { inner, cap } = whole.as_any_mut().downcast_mut::<Whole<I>>().unwrap();
+    let _kept: &'static mut Cap = cap;
+    inner
+}
+
+#[cfg(feature = "extern-abort-hook")]
+fn on_abort(_: &extend_mut::AbortInfo<'_>) {
+    eprintln!("extern hook ran");
+}
+
+#[cfg(feature = "extern-abort-hook")]
+extend_mut::abort_hook!(on_abort);
+
+fn run(case: &str) {
+    match case {
+        "pointer_changed" => {
+            let mut x = 0;
+            extend_mut(&mut x, |_| Box::leak(Box::new(1)));
+        }
+        "unwind" => {
+            let mut x = 0;
+            extend_mut(&mut x, |x| -> &mut i32 {
+                if *x == 0 {
+                    panic!("closure failed");
+                }
+                x
+            });
+        }
+        "tuple_pointer_changed" => {
+            let (mut x, mut y) = (0, 0);
+            (&mut x, &mut y).extend_mut(|(x, _)| (x, Box::leak(Box::new(1))));
+        }
+        "array_swapped" => {
+            let (mut x, mut y) = (0, 0);
+            [&mut x, &mut y].extend_mut(|[x, y]| [y, x]);
+        }
+        #[cfg(feature = "alloc")]
+        "vec_truncated" => {
+            let (mut x, mut y) = (0, 0);
+            vec![&mut x, &mut y].extend_mut(|mut v| {
+                v.pop();
+                v
+            });
+        }
+        "split_swapped" => {
+            let mut buf = [0u8; 4];
+            extend_mut_split(&mut buf, 2, |l, r| (r, l));
+        }
+        "option_taken" => {
+            let mut x = 0;
+            Some(&mut x).extend_mut(|_| None);
+        }
+        "reclaim_rejected" => {
+            let mut x = 0;
+            (&mut x, Rejected).extend_mut(|it| it);
+        }
+        "unchecked_swapped" => {
+            let (mut x, mut y) = (0, 0);
+            unsafe { [&mut x, &mut y].extend_mut_unchecked(|[x, y]| [y, x]) };
+        }
+        #[cfg(feature = "async")]
+        #[allow(unused_unsafe)]
+        "dropped_future" => {
+            let mut x = 0;
+            let fut = unsafe {
+                extend_mut::extend_mut_async(&mut x, async |x| {
+                    std::future::pending::<()>().await;
+                    x
+                })
+            };
+            // Nothing is lent before the first poll, so poll it once.
+            let _ = poll_once(fut);
+        }
+        #[cfg(all(feature = "async", feature = "assume-non-forget"))]
+        "tuple_future_dropped" => {
+            use std::{
+                pin::pin,
+                task::{Context, Waker},
+            };
+
+            let (mut x, mut y) = (0, 0);
+            let fut = (&mut x, &mut y).extend_mut_async(async |it| {
+                std::future::pending::<()>().await;
+                it
+            });
+            let _ = pin!(fut).poll(&mut Context::from_waker(Waker::noop()));
+        }
+        #[cfg(feature = "stable-async")]
+        #[allow(unused_unsafe)]
+        "block_on_drop_pointer_changed" => {
+            let mut x = 0;
+            let fut = unsafe {
+                extend_mut::extend_mut_future(&mut x, |_| async {
+                    let mut yielded = false;
+                    std::future::poll_fn(|cx| match std::mem::replace(&mut yielded, true) {
+                        true => std::task::Poll::Ready(()),
+                        false => {
+                            cx.waker().wake_by_ref();
+                            std::task::Poll::Pending
+                        }
+                    })
+                    .await;
+                    Box::leak(Box::new(1))
+                })
+            };
+            let _ = poll_once(fut.block_on_drop());
+        }
+        "dyn_field_returned" => {
+            let mut whole = Whole {
+                inner: 0u32,
+                cap: Cap,
+            };
+            extend_mut(&mut whole as &mut dyn Part, keep_cap::<u32>);
+        }
+        "zero_sized_dyn_field_returned" => {
+            let mut whole = Whole {
+                inner: (),
+                cap: Cap,
+            };
+            extend_mut(&mut whole as &mut dyn Part, keep_cap::<()>);
+        }
+        #[cfg(feature = "std")]
+        "hook" => {
+            extend_mut::set_abort_hook(|info| match info.error() {
+                ExtendMutError::PointerChanged { type_name, .. } => {
+                    eprintln!("hook: pointer changed for {type_name}")
+                }
+                _ => unreachable!(),
+            });
+            run("pointer_changed");
+        }
+        #[cfg(feature = "extern-abort-hook")]
+        "extern_hook" => run("pointer_changed"),
+        #[cfg(feature = "std")]
+        "unwinding_hook" => {
+            extend_mut::set_abort_hook(|_| panic!("hook failed"));
+            run("pointer_changed");
+        }
+        _ => unreachable!(),
+    }
+}
+
+/// Polls `fut` once and drops it.
+#[cfg(feature = "stable-async")]
+fn poll_once<F: Future>(fut: F) -> std::task::Poll<F::Output> {
+    let cx = &mut std::task::Context::from_waker(std::task::Waker::noop());
+    std::pin::pin!(fut).poll(cx)
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn pointer_changed() {
+    if let Some(output) = run_case("pointer_changed") {
+        let stderr = stderr(&output);
+        assert!(stderr.contains("ExtendMut: Pointer changed (`i32` at tests/abort.rs:"));
+        assert!(stderr.contains("stack backtrace:"));
+    }
+}
+
+#[test]
+fn tuple_pointer_changed() {
+    if let Some(output) = run_case("tuple_pointer_changed") {
+        assert!(stderr(&output).contains("ExtendMut: Pointer changed (`i32` at tests/abort.rs:"));
+    }
+}
+
+#[test]
+fn array_swapped() {
+    if let Some(output) = run_case("array_swapped") {
+        assert!(stderr(&output).contains("ExtendMut: Pointer changed (`i32` at tests/abort.rs:"));
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn vec_truncated() {
+    if let Some(output) = run_case("vec_truncated") {
+        assert!(stderr(&output).contains("ExtendMut: Pointer changed (`i32` at tests/abort.rs:"));
+    }
+}
+
+#[test]
+fn split_swapped() {
+    if let Some(output) = run_case("split_swapped") {
+        assert!(stderr(&output).contains("ExtendMut: Pointer changed (`[u8]` at tests/abort.rs:"));
+    }
+}
+
+#[test]
+fn option_taken() {
+    if let Some(output) = run_case("option_taken") {
+        let stderr = stderr(&output);
+        assert!(stderr.contains("ExtendMut: Pointer changed (`core::option::Option<&mut i32>` at"));
+    }
+}
+
+#[test]
+fn reclaim_rejected() {
+    if let Some(output) = run_case("reclaim_rejected") {
+        let stderr = stderr(&output);
+        assert!(
+            stderr.contains("ExtendMut: Pointer changed (`abort::Rejected` at tests/abort.rs:")
+        );
+    }
+}
+
+#[test]
+#[cfg(debug_assertions)]
+fn unchecked_swapped() {
+    if let Some(output) = run_case("unchecked_swapped") {
+        assert!(stderr(&output).contains("ExtendMut: Pointer changed (`i32` at tests/abort.rs:"));
+    }
+}
+
+#[test]
+#[cfg(feature = "async")]
+fn dropped_future() {
+    if let Some(output) = run_case("dropped_future") {
+        assert!(stderr(&output).contains("before it yields Poll::Ready (`i32` at tests/abort.rs:"));
+    }
+}
+
+#[test]
+#[cfg(all(feature = "async", feature = "assume-non-forget"))]
+fn tuple_future_dropped() {
+    if let Some(output) = run_case("tuple_future_dropped") {
+        let stderr = stderr(&output);
+        assert!(stderr.contains("before it yields Poll::Ready (`(&mut i32, &mut i32)` at"));
+    }
+}
+
+#[test]
+#[cfg(feature = "stable-async")]
+fn block_on_drop_pointer_changed() {
+    if let Some(output) = run_case("block_on_drop_pointer_changed") {
+        assert!(stderr(&output).contains("ExtendMut: Pointer changed (`i32` at tests/abort.rs:"));
+    }
+}
+
+#[test]
+fn dyn_field_returned() {
+    if let Some(output) = run_case("dyn_field_returned") {
+        assert!(stderr(&output).contains("ExtendMut: Pointer changed (`dyn abort::Part` at"));
+    }
+}
+
+#[test]
+fn zero_sized_dyn_field_returned() {
+    if let Some(output) = run_case("zero_sized_dyn_field_returned") {
+        assert!(stderr(&output).contains("ExtendMut: Pointer changed (`dyn abort::Part` at"));
+    }
+}
+
+#[test]
+fn unwind() {
+    if let Some(output) = run_case("unwind") {
+        let stderr = stderr(&output);
+        assert!(stderr.contains("ExtendMut: Function cannot unwind (`i32` at tests/abort.rs:"));
+        assert!(stderr.contains("closure failed"));
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn hook() {
+    if let Some(output) = run_case("hook") {
+        let stderr = stderr(&output);
+        assert!(stderr.contains("hook: pointer changed for i32"));
+        assert!(!stderr.contains("ExtendMut: Pointer changed"));
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn unwinding_hook() {
+    if let Some(output) = run_case("unwinding_hook") {
+        assert!(stderr(&output).contains("hook failed"));
+    }
+}
+
+#[cfg(feature = "extern-abort-hook")]
+#[test]
+fn extern_hook() {
+    if let Some(output) = run_case("extern_hook") {
+        let stderr = stderr(&output);
+        assert!(stderr.contains("extern hook ran"));
+        // The default message is still printed after the extern hook.
+        assert!(stderr.contains("ExtendMut: Pointer changed (`i32` at tests/abort.rs:"));
+    }
+}
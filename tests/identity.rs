@@ -0,0 +1,92 @@
+//! Identity checks for wide references that come back unchanged, exercised from
+//! outside the crate so that vtables and slice metadata are produced in a different
+//! crate than `extend_mut`.
+//!
+//! References that come back with different metadata abort, see the `dyn_field_returned`
+//! cases in `tests/abort.rs`. A trait object rebuilt from its concrete type may or may
+//! not get the same vtable, so there is no test for that.
+
+use extend_mut::{ExtendMut, extend_mut};
+
+#[cfg(feature = "extern-abort-hook")]
+extend_mut::abort_hook!(|_| {});
+
+trait Counter {
+    fn bump(&mut self) -> u32;
+}
+
+struct Hits(u32);
+
+impl Counter for Hits {
+    fn bump(&mut self) -> u32 {
+        self.0 += 1;
+        self.0
+    }
+}
+
+struct Silent;
+
+impl Counter for Silent {
+    fn bump(&mut self) -> u32 {
+        0
+    }
+}
+
+fn bump_static(counter: &'static mut dyn Counter) -> &'static mut dyn Counter {
+    counter.bump();
+    counter
+}
+
+#[test]
+fn dyn_trait() {
+    let mut hits = Hits(0);
+    let counter: &mut dyn Counter = &mut hits;
+
+    extend_mut(&mut *counter, bump_static);
+    let n = counter.extend_mut(|c| {
+        let n = c.bump();
+        (c, n)
+    });
+    assert_eq!(n, 2);
+    assert_eq!(hits.0, 2);
+}
+
+#[test]
+fn zero_sized_dyn_trait() {
+    let mut silent = Silent;
+    let mut hits = Hits(0);
+
+    extend_mut(&mut silent as &mut dyn Counter, bump_static);
+    let () = (
+        &mut silent as &mut dyn Counter,
+        &mut hits as &mut dyn Counter,
+    )
+        .extend_mut(|(s, h)| (bump_static(s), bump_static(h)));
+    assert_eq!(hits.0, 1);
+}
+
+#[test]
+fn slices_and_str() {
+    let mut buf = [1u8, 2, 3, 4];
+    let mut s = String::from("hello");
+
+    let sum = extend_mut(&mut buf[..], |b: &'static mut [u8]| {
+        let sum: u8 = b.iter().sum();
+        (b, sum)
+    });
+    assert_eq!(sum, 10);
+
+    extend_mut(&mut buf[1..3], |b| {
+        b.reverse();
+        b
+    });
+    assert_eq!(buf, [1, 3, 2, 4]);
+
+    extend_mut(s.as_mut_str(), |s: &'static mut str| {
+        s.make_ascii_uppercase();
+        s
+    });
+    assert_eq!(s, "HELLO");
+
+    let () = (&mut buf[..], s.as_mut_str()).extend_mut(|it| it);
+}